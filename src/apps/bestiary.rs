@@ -0,0 +1,593 @@
+use crate::apps::initiative_tracker::InitiativeTrackerEntry;
+use crate::dice::DiceExpr;
+use eframe::egui;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+/// Key under which the bestiary's files are kept in eframe's persistent storage.
+const STORAGE_KEY: &str = "bestiary";
+
+/// A few SRD creatures that ship with the app, copied into the config dir on first run.
+const BUNDLED_BESTIARY: &str = include_str!("../../resources/srd_monsters.json");
+
+const ABILITY_NAMES: [&str; 6] = ["STR", "DEX", "CON", "INT", "WIS", "CHA"];
+
+/// A named trait, action or legendary action.
+#[derive(Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Feature {
+    pub name: String,
+    pub desc: String,
+}
+
+/// A creature's stat block. Reads both the 5e-database layout
+/// (e.g. `armor_class: [{ "value": 15 }]`) and the flatter Open5e one.
+#[derive(Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct StatBlock {
+    pub name: String,
+    pub size: String,
+    #[serde(rename = "type")]
+    pub creature_type: String,
+    pub alignment: String,
+    #[serde(deserialize_with = "deserialize_armor_class")]
+    pub armor_class: i32,
+    /// Average hit points.
+    pub hit_points: i32,
+    /// Hit dice, e.g. "7d10"; Open5e includes the Constitution bonus here.
+    pub hit_dice: String,
+    /// The full HP formula in 5e-database files, e.g. "7d10+21".
+    pub hit_points_roll: String,
+    /// e.g. `{"walk": "30 ft.", "fly": "80 ft."}`.
+    pub speed: serde_json::Map<String, serde_json::Value>,
+    pub strength: i32,
+    pub dexterity: i32,
+    pub constitution: i32,
+    pub intelligence: i32,
+    pub wisdom: i32,
+    pub charisma: i32,
+    /// e.g. "1/4"; numbers like 0.25 are written as fractions.
+    #[serde(deserialize_with = "deserialize_challenge_rating")]
+    pub challenge_rating: String,
+    /// An object with `passive_perception`, or text like "darkvision 60 ft., passive Perception 9".
+    pub senses: serde_json::Value,
+    #[serde(deserialize_with = "deserialize_text_list")]
+    pub damage_resistances: Vec<String>,
+    #[serde(deserialize_with = "deserialize_text_list")]
+    pub damage_immunities: Vec<String>,
+    #[serde(deserialize_with = "deserialize_text_list")]
+    pub damage_vulnerabilities: Vec<String>,
+    pub special_abilities: Vec<Feature>,
+    pub actions: Vec<Feature>,
+    pub legendary_actions: Vec<Feature>,
+    /// Open5e's introduction to the legendary actions, e.g. "The dragon can take
+    /// 3 legendary actions, choosing from the options below." May be null.
+    pub legendary_desc: Option<String>,
+}
+
+impl StatBlock {
+    fn abilities(&self) -> [i32; 6] {
+        [
+            self.strength,
+            self.dexterity,
+            self.constitution,
+            self.intelligence,
+            self.wisdom,
+            self.charisma,
+        ]
+    }
+
+    /// The modifier for an ability score, e.g. 14 -> +2.
+    pub fn modifier(score: i32) -> i32 {
+        (score - 10).div_euclid(2)
+    }
+
+    pub fn passive_perception(&self) -> i32 {
+        match &self.senses {
+            serde_json::Value::Object(senses) => senses
+                .get("passive_perception")
+                .and_then(|value| value.as_i64())
+                .map(|value| value as i32),
+            serde_json::Value::String(senses) => {
+                let lower = senses.to_lowercase();
+                lower.find("passive perception").and_then(|at| {
+                    lower[at + "passive perception".len()..]
+                        .split(|c: char| !c.is_ascii_digit())
+                        .find(|part| !part.is_empty())
+                        .and_then(|number| number.parse().ok())
+                })
+            }
+            _ => None,
+        }
+        .unwrap_or_else(|| 10 + Self::modifier(self.wisdom))
+    }
+
+    /// Legendary actions per round, from text like "can take 3 legendary actions".
+    /// Stat blocks that don't say get the usual three.
+    pub fn legendary_actions_per_round(&self) -> u32 {
+        if self.legendary_actions.is_empty() {
+            return 0;
+        }
+        let descriptions = self
+            .special_abilities
+            .iter()
+            .chain(&self.legendary_actions)
+            .map(|feature| feature.desc.as_str());
+        self.legendary_desc
+            .as_deref()
+            .into_iter()
+            .chain(descriptions)
+            .find_map(|text| {
+                let lower = text.to_lowercase();
+                let at = lower.find("can take ")?;
+                let mut words = lower[at + "can take ".len()..].split_whitespace();
+                let count = words.next()?;
+                if !words.next()?.starts_with("legendary") {
+                    return None;
+                }
+                count.parse().ok().or_else(|| {
+                    let words = ["one", "two", "three", "four", "five"];
+                    words.iter().position(|w| *w == count).map(|n| n as u32 + 1)
+                })
+            })
+            .unwrap_or(3)
+    }
+
+    /// e.g. "30 ft., fly 80 ft.".
+    pub fn speed_text(&self) -> String {
+        // Walking speed first, unlabeled, as in the books.
+        let walk = self.speed.iter().filter(|(mode, _)| *mode == "walk");
+        let others = self.speed.iter().filter(|(mode, _)| *mode != "walk");
+        walk.chain(others)
+            .map(|(mode, value)| {
+                let value = match value {
+                    serde_json::Value::String(text) => text.clone(),
+                    serde_json::Value::Number(feet) => format!("{} ft.", feet),
+                    other => other.to_string(),
+                };
+                if mode == "walk" {
+                    value
+                } else {
+                    format!("{} {}", mode, value)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// The HP formula, e.g. "7d10+21"; empty if the file has none.
+    pub fn hit_point_formula(&self) -> &str {
+        if self.hit_points_roll.trim().is_empty() {
+            self.hit_dice.trim()
+        } else {
+            self.hit_points_roll.trim()
+        }
+    }
+
+    /// Rolls the HP formula, falling back to the average if there is none.
+    pub fn roll_hit_points(&self, rng: &mut fastrand::Rng) -> i32 {
+        match DiceExpr::parse(self.hit_point_formula()) {
+            Ok(dice) if !self.hit_point_formula().is_empty() => dice.roll(rng).total.max(1),
+            _ => self.hit_points,
+        }
+    }
+}
+
+/// Accepts a plain number or the 5e-database list of armor classes (the first one counts).
+fn deserialize_armor_class<'de, D>(deserializer: D) -> Result<i32, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    struct ArmorClassEntry {
+        value: i32,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum ArmorClass {
+        Number(i32),
+        Entries(Vec<ArmorClassEntry>),
+    }
+
+    Ok(match ArmorClass::deserialize(deserializer)? {
+        ArmorClass::Number(value) => value,
+        ArmorClass::Entries(entries) => entries.first().map_or(10, |entry| entry.value),
+    })
+}
+
+/// Accepts "1/4" or 0.25.
+fn deserialize_challenge_rating<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum ChallengeRating {
+        Number(f64),
+        Text(String),
+    }
+
+    Ok(match ChallengeRating::deserialize(deserializer)? {
+        ChallengeRating::Text(text) => text,
+        ChallengeRating::Number(cr) if cr > 0.0 && cr < 1.0 => format!("1/{}", (1.0 / cr).round()),
+        ChallengeRating::Number(cr) => cr.to_string(),
+    })
+}
+
+/// Accepts a list of strings or Open5e's one string with `;` between entries (empty for none).
+fn deserialize_text_list<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum TextList {
+        List(Vec<String>),
+        Text(String),
+    }
+
+    Ok(match TextList::deserialize(deserializer)? {
+        TextList::List(list) => list,
+        TextList::Text(text) => text
+            .split(';')
+            .map(str::trim)
+            .filter(|part| !part.is_empty())
+            .map(str::to_string)
+            .collect(),
+    })
+}
+
+/// Reads the stat blocks in a JSON file: either a list of creatures or an
+/// object holding one under `monsters` or `results`.
+fn load_stat_blocks(path: &Path) -> Result<Vec<StatBlock>, Box<dyn Error>> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum BestiaryFile {
+        List(Vec<StatBlock>),
+        Wrapped {
+            #[serde(alias = "results")]
+            monsters: Vec<StatBlock>,
+        },
+    }
+
+    let data_string = std::fs::read_to_string(path)?;
+    Ok(match serde_json::from_str(&data_string)? {
+        BestiaryFile::List(blocks) | BestiaryFile::Wrapped { monsters: blocks } => blocks,
+    })
+}
+
+/// Where the bundled stat blocks are read from: `<config dir>/ttrpg_app/srd_monsters.json`.
+/// Loading the bestiary writes them there if that file hasn't been created yet.
+fn default_bestiary_path() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_default()
+        .join("ttrpg_app")
+        .join("srd_monsters.json")
+}
+
+fn write_bundled_bestiary(path: &Path) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, BUNDLED_BESTIARY)
+}
+
+/// A stat block together with the file it came from.
+struct LoadedStatBlock {
+    block: StatBlock,
+    source: PathBuf,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Bestiary {
+    /// JSON files to load stat blocks from, in the order they were added.
+    files: Vec<PathBuf>,
+    /// Roll each creature's HP formula instead of using the average.
+    roll_hit_points: bool,
+    #[serde(skip)]
+    search: String,
+    #[serde(skip)]
+    creatures: Vec<LoadedStatBlock>,
+    /// Whether `files` have been read since startup.
+    #[serde(skip)]
+    loaded: bool,
+    /// Index into `creatures` of the stat block on display.
+    #[serde(skip)]
+    selected: Option<usize>,
+    /// Load failures and other messages for the status line.
+    #[serde(skip)]
+    status: Option<String>,
+    /// Creatures waiting to be picked up by the initiative tracker.
+    #[serde(skip)]
+    outgoing: Vec<InitiativeTrackerEntry>,
+    #[serde(skip)]
+    rng: fastrand::Rng,
+}
+
+impl Default for Bestiary {
+    fn default() -> Self {
+        Self {
+            files: vec![default_bestiary_path()],
+            roll_hit_points: false,
+            search: String::new(),
+            creatures: Vec::new(),
+            loaded: false,
+            selected: None,
+            status: None,
+            outgoing: Vec::new(),
+            rng: fastrand::Rng::new(),
+        }
+    }
+}
+
+impl Bestiary {
+    /// Restores the list of files from eframe's storage.
+    pub fn load_state(storage: &dyn eframe::Storage) -> Option<Self> {
+        eframe::get_value(storage, STORAGE_KEY)
+    }
+
+    /// Writes the list of files to eframe's storage.
+    pub fn save_state(&self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, STORAGE_KEY, self);
+    }
+
+    /// Creatures added with "Add to encounter" since the last call.
+    pub fn take_combatants(&mut self) -> Vec<InitiativeTrackerEntry> {
+        std::mem::take(&mut self.outgoing)
+    }
+
+    /// Re-reads every file, sorting the creatures by name.
+    fn reload(&mut self) {
+        self.creatures.clear();
+        self.selected = None;
+        let mut failures = Vec::new();
+        for path in &self.files {
+            if *path == default_bestiary_path()
+                && !path.exists()
+                && let Err(e) = write_bundled_bestiary(path)
+            {
+                eprintln!("Failed to create bestiary {}: {}", path.display(), e);
+            }
+            match load_stat_blocks(path) {
+                Ok(blocks) => {
+                    self.creatures
+                        .extend(blocks.into_iter().map(|block| LoadedStatBlock {
+                            block,
+                            source: path.clone(),
+                        }))
+                }
+                Err(e) => {
+                    eprintln!("Failed to load stat blocks from {}: {}", path.display(), e);
+                    failures.push(path.display().to_string());
+                }
+            }
+        }
+        self.creatures
+            .sort_by(|a, b| a.block.name.cmp(&b.block.name));
+        self.status =
+            (!failures.is_empty()).then(|| format!("Couldn't read {}", failures.join(", ")));
+        self.loaded = true;
+    }
+
+    /// Whether a creature matches the search box by name, type or CR.
+    fn matches_search(&self, block: &StatBlock) -> bool {
+        let search = self.search.trim().to_lowercase();
+        search.is_empty()
+            || block.name.to_lowercase().contains(&search)
+            || block.creature_type.to_lowercase().contains(&search)
+            || search
+                .strip_prefix("cr ")
+                .is_some_and(|cr| block.challenge_rating == cr.trim())
+    }
+
+    /// Queues a creature for the initiative tracker.
+    fn add_to_encounter(&mut self, index: usize) {
+        let block = &self.creatures[index].block;
+        let hp = if self.roll_hit_points {
+            block.roll_hit_points(&mut self.rng)
+        } else {
+            block.hit_points
+        };
+        self.outgoing
+            .push(InitiativeTrackerEntry::from_stat_block(block, hp));
+        self.status = Some(format!("Added {} ({} HP) to the encounter", block.name, hp));
+    }
+
+    fn files_ui(&mut self, ui: &mut egui::Ui) {
+        let mut file_to_remove = None;
+        for (i, path) in self.files.iter().enumerate() {
+            ui.horizontal(|ui| {
+                if ui
+                    .small_button("X")
+                    .on_hover_text("Stop using this file")
+                    .clicked()
+                {
+                    file_to_remove = Some(i);
+                }
+                ui.label(path.display().to_string());
+            });
+        }
+        if let Some(i) = file_to_remove {
+            self.files.remove(i);
+            self.reload();
+        }
+        if ui.button("Add File...").clicked()
+            && let Some(path) = rfd::FileDialog::new()
+                .add_filter("JSON", &["json"])
+                .pick_file()
+        {
+            if !self.files.contains(&path) {
+                self.files.push(path);
+            }
+            self.reload();
+        }
+    }
+
+    fn stat_block_ui(&mut self, ui: &mut egui::Ui, index: usize) {
+        let LoadedStatBlock { block, source } = &self.creatures[index];
+        ui.heading(&block.name);
+        ui.label(
+            egui::RichText::new(format!(
+                "{} {}, {}",
+                block.size, block.creature_type, block.alignment
+            ))
+            .italics(),
+        );
+        ui.weak(source.display().to_string());
+        ui.separator();
+        ui.label(format!("Armor Class {}", block.armor_class));
+        ui.label(match block.hit_point_formula() {
+            "" => format!("Hit Points {}", block.hit_points),
+            formula => format!("Hit Points {} ({})", block.hit_points, formula),
+        });
+        ui.label(format!("Speed {}", block.speed_text()));
+        ui.separator();
+        egui::Grid::new("ability_scores").show(ui, |ui| {
+            for name in ABILITY_NAMES {
+                ui.strong(name);
+            }
+            ui.end_row();
+            for score in block.abilities() {
+                ui.label(format!("{} ({:+})", score, StatBlock::modifier(score)));
+            }
+            ui.end_row();
+        });
+        ui.separator();
+        for (label, list) in [
+            ("Damage Vulnerabilities", &block.damage_vulnerabilities),
+            ("Damage Resistances", &block.damage_resistances),
+            ("Damage Immunities", &block.damage_immunities),
+        ] {
+            if !list.is_empty() {
+                ui.label(format!("{} {}", label, list.join("; ")));
+            }
+        }
+        ui.label(format!("Passive Perception {}", block.passive_perception()));
+        ui.label(format!("Challenge {}", block.challenge_rating));
+        for (heading, features) in [
+            (None, &block.special_abilities),
+            (Some("Actions"), &block.actions),
+            (Some("Legendary Actions"), &block.legendary_actions),
+        ] {
+            if features.is_empty() {
+                continue;
+            }
+            ui.separator();
+            if let Some(heading) = heading {
+                ui.strong(heading);
+            }
+            if heading == Some("Legendary Actions")
+                && let Some(desc) = &block.legendary_desc
+            {
+                ui.label(desc);
+            }
+            for feature in features {
+                ui.horizontal_wrapped(|ui| {
+                    ui.label(egui::RichText::new(format!("{}.", feature.name)).strong());
+                    ui.label(&feature.desc);
+                });
+            }
+        }
+    }
+
+    pub fn update_ui(&mut self, ctx: &egui::Context) {
+        if !self.loaded {
+            self.reload();
+        }
+
+        egui::SidePanel::left("bestiary_list")
+            .resizable(true)
+            .default_width(220.0)
+            .show(ctx, |ui| {
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.search)
+                        .hint_text("Search name, type or \"cr 1/4\""),
+                );
+                ui.separator();
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for i in 0..self.creatures.len() {
+                        let block = &self.creatures[i].block;
+                        if !self.matches_search(block) {
+                            continue;
+                        }
+                        let text = format!("{} (CR {})", block.name, block.challenge_rating);
+                        if ui
+                            .selectable_label(self.selected == Some(i), text)
+                            .clicked()
+                        {
+                            self.selected = Some(i);
+                        }
+                    }
+                });
+            });
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.menu_button("Files", |ui| self.files_ui(ui));
+                if ui.button("Reload").clicked() {
+                    self.reload();
+                }
+                ui.checkbox(&mut self.roll_hit_points, "Roll HP")
+                    .on_hover_text("Roll the HP formula instead of using the average");
+                if let Some(index) = self.selected
+                    && ui.button("Add to encounter").clicked()
+                {
+                    self.add_to_encounter(index);
+                }
+            });
+            if let Some(status) = &self.status {
+                ui.label(status);
+            }
+            ui.separator();
+            match self.selected {
+                Some(index) => {
+                    egui::ScrollArea::vertical().show(ui, |ui| self.stat_block_ui(ui, index));
+                }
+                None if self.creatures.is_empty() => {
+                    ui.label("No stat blocks loaded. Add a JSON file under Files.");
+                }
+                None => {
+                    ui.label("Pick a creature from the list.");
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundled_bestiary_parses() {
+        let blocks: Vec<StatBlock> = serde_json::from_str(BUNDLED_BESTIARY).unwrap();
+        assert!(!blocks.is_empty());
+    }
+
+    fn legendary(desc: &str, actions: usize) -> StatBlock {
+        StatBlock {
+            legendary_desc: Some(desc.to_string()),
+            legendary_actions: vec![Feature::default(); actions],
+            ..StatBlock::default()
+        }
+    }
+
+    #[test]
+    fn reads_the_number_of_legendary_actions() {
+        let text = "The tarrasque can take 5 legendary actions, choosing from the options below.";
+        assert_eq!(legendary(text, 3).legendary_actions_per_round(), 5);
+        let text = "It can take two legendary actions per round.";
+        assert_eq!(legendary(text, 2).legendary_actions_per_round(), 2);
+    }
+
+    #[test]
+    fn legendary_actions_default_to_three() {
+        assert_eq!(legendary("", 3).legendary_actions_per_round(), 3);
+        let text = "The lich can take the Dash action as a bonus action.";
+        assert_eq!(legendary(text, 3).legendary_actions_per_round(), 3);
+        assert_eq!(legendary("", 0).legendary_actions_per_round(), 0);
+    }
+}
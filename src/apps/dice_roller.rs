@@ -0,0 +1,161 @@
+use crate::dice::{DiceExpr, Roll};
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+/// Key under which the roll history is kept in eframe's persistent storage.
+const STORAGE_KEY: &str = "dice_roller";
+
+/// How many rolls the history panel keeps.
+const MAX_HISTORY: usize = 100;
+
+/// The one-click dice shown under the input.
+const QUICK_DICE: [u32; 7] = [4, 6, 8, 10, 12, 20, 100];
+
+/// A roll as it is shown in the history panel.
+#[derive(Clone, Serialize, Deserialize)]
+struct HistoryEntry {
+    /// The notation as the user typed it.
+    notation: String,
+    roll: Roll,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct DiceRoller {
+    /// The dice notation in the input box.
+    notation: String,
+    /// Past rolls, newest first.
+    history: Vec<HistoryEntry>,
+    /// The parse error for the last roll attempt, if any.
+    #[serde(skip)]
+    error: Option<String>,
+    #[serde(skip)]
+    rng: fastrand::Rng,
+}
+
+impl Default for DiceRoller {
+    fn default() -> Self {
+        Self {
+            notation: "1d20".to_string(),
+            history: Vec::new(),
+            error: None,
+            rng: fastrand::Rng::new(),
+        }
+    }
+}
+
+impl DiceRoller {
+    /// Restores the roll history from eframe's storage.
+    pub fn load_state(storage: &dyn eframe::Storage) -> Option<Self> {
+        eframe::get_value(storage, STORAGE_KEY)
+    }
+
+    /// Writes the roll history to eframe's storage.
+    pub fn save_state(&self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, STORAGE_KEY, self);
+    }
+
+    /// Parses and rolls `notation`, adding the result to the history.
+    fn roll(&mut self, notation: &str) {
+        match DiceExpr::parse(notation) {
+            Ok(expr) => {
+                let roll = expr.roll(&mut self.rng);
+                self.history.insert(
+                    0,
+                    HistoryEntry {
+                        notation: notation.trim().to_string(),
+                        roll,
+                    },
+                );
+                self.history.truncate(MAX_HISTORY);
+                self.error = None;
+            }
+            Err(e) => self.error = Some(e.to_string()),
+        }
+    }
+
+    pub fn update_ui(&mut self, ctx: &egui::Context) {
+        egui::SidePanel::right("roll_history")
+            .resizable(true)
+            .default_width(260.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.heading("History");
+                    if ui.button("Clear").clicked() {
+                        self.history.clear();
+                    }
+                });
+                ui.separator();
+                // Re-rolling adds to the history, so do it after the loop.
+                let mut reroll: Option<String> = None;
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for entry in &self.history {
+                        ui.horizontal(|ui| {
+                            if ui.small_button("↻").on_hover_text("Roll again").clicked() {
+                                reroll = Some(entry.notation.clone());
+                            }
+                            ui.strong(entry.roll.total.to_string());
+                            ui.label(&entry.notation);
+                        });
+                        ui.small(entry.roll.to_string());
+                        ui.separator();
+                    }
+                });
+                if let Some(notation) = reroll {
+                    self.roll(&notation);
+                }
+            });
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.heading("Dice Roller");
+            ui.separator();
+
+            ui.horizontal(|ui| {
+                let response = ui.add(
+                    egui::TextEdit::singleline(&mut self.notation)
+                        .hint_text("e.g., 2d6+3, 4d6kh3, 1d20+5 adv"),
+                );
+                // Pressing Enter in the box rolls, just like the button.
+                let submitted =
+                    response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                if ui.button("Roll").clicked() || submitted {
+                    let notation = self.notation.clone();
+                    self.roll(&notation);
+                }
+            });
+            if let Some(error) = &self.error {
+                ui.colored_label(egui::Color32::from_rgb(255, 0, 0), error);
+            }
+
+            ui.horizontal(|ui| {
+                for sides in QUICK_DICE {
+                    if ui.button(format!("d{}", sides)).clicked() {
+                        self.roll(&format!("1d{}", sides));
+                    }
+                }
+            });
+            ui.separator();
+
+            // Show the latest roll large so it can be read across the table.
+            if let Some(latest) = self.history.first() {
+                ui.label(&latest.notation);
+                ui.label(
+                    egui::RichText::new(latest.roll.total.to_string())
+                        .size(48.0)
+                        .strong(),
+                );
+                ui.label(latest.roll.to_string());
+            }
+
+            ui.add_space(12.0);
+            egui::CollapsingHeader::new("Notation help").show(ui, |ui| {
+                ui.label("2d6+3 — dice and flat modifiers");
+                ui.label("d20, d% — one die; % is a d100");
+                ui.label("4d6kh3 / 4d6dl1 — keep highest 3 / drop lowest 1 (also kl, dh)");
+                ui.label("3d6! — exploding dice: a max roll adds another die");
+                ui.label("2d6r2 — reroll each die showing 2 or less, once");
+                ui.label("1d20+5 adv / dis — roll the d20 twice, keep higher / lower");
+            });
+        });
+    }
+}
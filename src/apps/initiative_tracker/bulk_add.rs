@@ -0,0 +1,232 @@
+use crate::dice::DiceExpr;
+use eframe::egui;
+
+/// How copies made by "Add Multiple" are told apart.
+#[derive(Clone, Copy, PartialEq)]
+pub enum CopyLabels {
+    /// "Goblin 1", "Goblin 2", ...
+    Numbers,
+    /// "Goblin A", "Goblin B", ...
+    Letters,
+}
+
+impl CopyLabels {
+    fn label(self, n: usize) -> String {
+        match self {
+            CopyLabels::Numbers => (n + 1).to_string(),
+            CopyLabels::Letters => {
+                // A..Z, then AA, AB, ... like spreadsheet columns.
+                let mut label = String::new();
+                let mut n = n + 1;
+                while n > 0 {
+                    n -= 1;
+                    label.insert(0, (b'A' + (n % 26) as u8) as char);
+                    n /= 26;
+                }
+                label
+            }
+        }
+    }
+
+    /// The `n` whose label is `label`, if it is one of these labels.
+    fn index(self, label: &str) -> Option<usize> {
+        let n = match self {
+            CopyLabels::Numbers => label.parse::<usize>().ok()?.checked_sub(1)?,
+            CopyLabels::Letters => {
+                let mut n = 0usize;
+                for c in label.chars() {
+                    if !c.is_ascii_uppercase() {
+                        return None;
+                    }
+                    n = n
+                        .checked_mul(26)?
+                        .checked_add((c as u8 - b'A') as usize + 1)?;
+                }
+                n.checked_sub(1)?
+            }
+        };
+        // "Goblin 007" isn't one of ours.
+        (self.label(n) == label).then_some(n)
+    }
+}
+
+/// The inputs of the "Add Multiple" window.
+pub struct BulkAddForm {
+    pub name: String,
+    pub count: usize,
+    /// A fixed number or dice rolled for each copy, e.g. "2d6" or "2d8+2".
+    pub hp: String,
+    pub armor_class: i32,
+    pub initiative_modifier: i32,
+    pub labels: CopyLabels,
+    /// Put the copies in one group that shares a turn.
+    pub as_group: bool,
+    pub error: Option<String>,
+}
+
+impl Default for BulkAddForm {
+    fn default() -> Self {
+        Self {
+            name: "Goblin".to_string(),
+            count: 4,
+            hp: "2d6".to_string(),
+            armor_class: 15,
+            initiative_modifier: 2,
+            labels: CopyLabels::Numbers,
+            as_group: false,
+            error: None,
+        }
+    }
+}
+
+impl BulkAddForm {
+    /// Shows the inputs. Returns true when "Add" is clicked.
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> bool {
+        egui::Grid::new("bulk_add_grid")
+            .num_columns(2)
+            .spacing([10.0, 4.0])
+            .show(ui, |ui| {
+                ui.label("Name:");
+                ui.text_edit_singleline(&mut self.name);
+                ui.end_row();
+
+                ui.label("Copies:");
+                ui.add(egui::DragValue::new(&mut self.count).range(1..=50));
+                ui.end_row();
+
+                ui.label("HP:");
+                ui.add(egui::TextEdit::singleline(&mut self.hp).hint_text("7 or 2d6"))
+                    .on_hover_text("A number, or dice rolled separately for each copy");
+                ui.end_row();
+
+                ui.label("AC:");
+                ui.add(egui::DragValue::new(&mut self.armor_class));
+                ui.end_row();
+
+                ui.label("Init Mod:");
+                ui.add(
+                    egui::DragValue::new(&mut self.initiative_modifier)
+                        .custom_formatter(|n, _| format!("{:+}", n)),
+                );
+                ui.end_row();
+
+                ui.label("Labels:");
+                ui.horizontal(|ui| {
+                    ui.radio_value(&mut self.labels, CopyLabels::Numbers, "1, 2, 3");
+                    ui.radio_value(&mut self.labels, CopyLabels::Letters, "A, B, C");
+                });
+                ui.end_row();
+
+                ui.label("");
+                ui.checkbox(&mut self.as_group, "One group sharing a turn");
+                ui.end_row();
+            });
+        if let Some(error) = &self.error {
+            ui.colored_label(egui::Color32::RED, error);
+        }
+        ui.button("Add").clicked()
+    }
+
+    /// An error message for the form if the name is blank, since the copies would
+    /// only be told apart by their labels.
+    pub fn check_name(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            Err("Name: enter a name for the copies".to_string())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// The HP dice, or an error message for the form.
+    pub fn hp_dice(&self) -> Result<DiceExpr, String> {
+        DiceExpr::parse(&self.hp).map_err(|e| format!("HP: {}", e))
+    }
+
+    /// `count` names like "Goblin 3" that none of `existing` uses. Labels go on
+    /// after the highest one taken, so a second batch doesn't reuse a dead goblin's name.
+    pub fn names(&self, existing: &[&str]) -> Vec<String> {
+        let base = self.name.trim();
+        let name_for = |n: usize| format!("{} {}", base, self.labels.label(n));
+        let taken = |n: usize| existing.contains(&name_for(n).as_str());
+        let mut n = existing
+            .iter()
+            .filter_map(|name| name.strip_prefix(base)?.strip_prefix(' '))
+            .filter_map(|label| self.labels.index(label))
+            .max()
+            .map_or(0, |n| n + 1);
+        let mut names = Vec::with_capacity(self.count);
+        while names.len() < self.count {
+            if !taken(n) {
+                names.push(name_for(n));
+            }
+            n += 1;
+        }
+        names
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn form(name: &str, count: usize, labels: CopyLabels) -> BulkAddForm {
+        BulkAddForm {
+            name: name.to_string(),
+            count,
+            labels,
+            ..BulkAddForm::default()
+        }
+    }
+
+    #[test]
+    fn letters_wrap_like_spreadsheet_columns() {
+        let labels: Vec<String> = [0, 1, 25, 26, 27, 51, 52, 701, 702]
+            .into_iter()
+            .map(|n| CopyLabels::Letters.label(n))
+            .collect();
+        assert_eq!(labels, ["A", "B", "Z", "AA", "AB", "AZ", "BA", "ZZ", "AAA"]);
+        for n in [0, 25, 26, 701, 702] {
+            assert_eq!(
+                CopyLabels::Letters.index(&CopyLabels::Letters.label(n)),
+                Some(n)
+            );
+        }
+        assert_eq!(CopyLabels::Letters.index("a"), None);
+        assert_eq!(CopyLabels::Numbers.label(0), "1");
+        assert_eq!(CopyLabels::Numbers.label(9), "10");
+    }
+
+    #[test]
+    fn numbers_from_one_without_existing_copies() {
+        let names = form(" Goblin ", 3, CopyLabels::Numbers).names(&["Orc 1", "Goblin Boss 1"]);
+        assert_eq!(names, ["Goblin 1", "Goblin 2", "Goblin 3"]);
+    }
+
+    #[test]
+    fn skips_taken_names() {
+        let existing = ["Goblin 1", "Goblin 2", "Orc 3"];
+        let names = form("Goblin", 2, CopyLabels::Numbers).names(&existing);
+        assert_eq!(names, ["Goblin 3", "Goblin 4"]);
+        let existing = ["Goblin A", "Goblin B"];
+        let names = form("Goblin", 1, CopyLabels::Letters).names(&existing);
+        assert_eq!(names, ["Goblin C"]);
+    }
+
+    #[test]
+    fn continues_after_the_highest_label() {
+        // Goblins 1 and 2 have died; the next batch doesn't reuse their names.
+        let names = form("Goblin", 2, CopyLabels::Numbers).names(&["Goblin 3"]);
+        assert_eq!(names, ["Goblin 4", "Goblin 5"]);
+        let names = form("Goblin", 2, CopyLabels::Letters).names(&["Goblin Z"]);
+        assert_eq!(names, ["Goblin AA", "Goblin AB"]);
+        let names = form("Goblin", 1, CopyLabels::Numbers).names(&["Goblin 12", "Goblin 012"]);
+        assert_eq!(names, ["Goblin 13"]);
+    }
+
+    #[test]
+    fn rejects_a_blank_name() {
+        assert!(form("Goblin", 1, CopyLabels::Numbers).check_name().is_ok());
+        assert!(form("", 1, CopyLabels::Numbers).check_name().is_err());
+        assert!(form("   ", 1, CopyLabels::Numbers).check_name().is_err());
+    }
+}
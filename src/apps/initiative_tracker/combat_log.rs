@@ -0,0 +1,289 @@
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+
+/// Something that happened during a fight.
+#[derive(Clone, Serialize, Deserialize)]
+pub enum CombatEvent {
+    Damage {
+        target: String,
+        amount: i32,
+        /// e.g. "fire"; `None` for untyped damage.
+        #[serde(default)]
+        damage_type: Option<String>,
+        /// How defenses changed the damage, e.g. "resisted".
+        #[serde(default)]
+        note: Option<String>,
+        hp_after: i32,
+        hp_max: i32,
+    },
+    Healing {
+        target: String,
+        amount: i32,
+        hp_after: i32,
+        hp_max: i32,
+    },
+    TempHp {
+        target: String,
+        amount: i32,
+    },
+    ConditionApplied {
+        target: String,
+        condition: String,
+    },
+    ConditionRemoved {
+        target: String,
+        condition: String,
+    },
+    ConditionExpired {
+        target: String,
+        condition: String,
+    },
+    DeathSave {
+        target: String,
+        /// e.g. "rolled 14: 2 successes, 1 failure".
+        outcome: String,
+    },
+    Died {
+        target: String,
+    },
+    ConcentrationSave {
+        target: String,
+        dc: i32,
+        /// e.g. "rolled 14: kept".
+        outcome: String,
+    },
+    ConcentrationLost {
+        target: String,
+        spell: String,
+    },
+    LegendaryAction {
+        name: String,
+        remaining: u32,
+    },
+    Delayed {
+        name: String,
+        /// Who they now act after.
+        after: String,
+    },
+    ActionReadied {
+        name: String,
+        trigger: String,
+    },
+    ReadiedActionTriggered {
+        name: String,
+        trigger: String,
+    },
+    Surprised {
+        name: String,
+    },
+    Revealed {
+        name: String,
+    },
+    CombatantAdded {
+        name: String,
+    },
+    CombatantRemoved {
+        name: String,
+    },
+    RoundStarted {
+        round: u32,
+    },
+    TurnStarted {
+        name: String,
+    },
+    /// "Previous Turn" went back to this turn.
+    TurnReversed {
+        name: String,
+    },
+    CombatReset,
+}
+
+impl CombatEvent {
+    /// A one-line, human readable description.
+    pub fn describe(&self) -> String {
+        match self {
+            CombatEvent::Damage {
+                target,
+                amount,
+                damage_type,
+                note,
+                hp_after,
+                hp_max,
+            } => {
+                let damage = match damage_type {
+                    Some(kind) => format!("{amount} {kind} damage"),
+                    None => format!("{amount} damage"),
+                };
+                let note = note.as_ref().map(|n| format!(", {n}")).unwrap_or_default();
+                format!("{target} takes {damage} ({hp_after}/{hp_max} HP{note})")
+            }
+            CombatEvent::Healing {
+                target,
+                amount,
+                hp_after,
+                hp_max,
+            } => format!("{target} heals {amount} HP ({hp_after}/{hp_max} HP)"),
+            CombatEvent::TempHp { target, amount } => {
+                format!("{target} gains {amount} temporary HP")
+            }
+            CombatEvent::ConditionApplied { target, condition } => {
+                format!("{target} is {condition}")
+            }
+            CombatEvent::ConditionRemoved { target, condition } => {
+                format!("{target} is no longer {condition}")
+            }
+            CombatEvent::ConditionExpired { target, condition } => {
+                format!("{target} is no longer {condition} (expired)")
+            }
+            CombatEvent::DeathSave { target, outcome } => {
+                format!("{target} makes a death save: {outcome}")
+            }
+            CombatEvent::Died { target } => format!("{target} dies"),
+            CombatEvent::ConcentrationSave {
+                target,
+                dc,
+                outcome,
+            } => format!("{target} makes a DC {dc} concentration save: {outcome}"),
+            CombatEvent::ConcentrationLost { target, spell } if spell.is_empty() => {
+                format!("{target} loses concentration")
+            }
+            CombatEvent::ConcentrationLost { target, spell } => {
+                format!("{target} loses concentration on {spell}")
+            }
+            CombatEvent::LegendaryAction { name, remaining } => {
+                format!("{name} takes a legendary action ({remaining} left)")
+            }
+            CombatEvent::Delayed { name, after } => {
+                format!("{name} delays until after {after}")
+            }
+            CombatEvent::ActionReadied { name, trigger } => {
+                format!("{name} readies an action: {trigger}")
+            }
+            CombatEvent::ReadiedActionTriggered { name, trigger } => {
+                format!("{name} takes their readied action: {trigger}")
+            }
+            CombatEvent::Surprised { name } => format!("{name} is surprised and loses their turn"),
+            CombatEvent::Revealed { name } => format!("{name} is revealed"),
+            CombatEvent::CombatantAdded { name } => format!("{name} joins the fight"),
+            CombatEvent::CombatantRemoved { name } => format!("{name} leaves the fight"),
+            CombatEvent::RoundStarted { round } => format!("Round {round} begins"),
+            CombatEvent::TurnStarted { name } => format!("{name}'s turn"),
+            CombatEvent::TurnReversed { name } => format!("Back to {name}'s turn"),
+            CombatEvent::CombatReset => "Combat reset".to_string(),
+        }
+    }
+}
+
+/// An event with when it happened.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct LogEntry {
+    pub time: DateTime<Local>,
+    pub round: u32,
+    pub event: CombatEvent,
+}
+
+/// Everything that happened in the fight, oldest first.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct CombatLog {
+    entries: Vec<LogEntry>,
+}
+
+impl CombatLog {
+    pub fn push(&mut self, round: u32, event: CombatEvent) {
+        self.entries.push(LogEntry {
+            time: Local::now(),
+            round,
+            event,
+        });
+    }
+
+    pub fn entries(&self) -> &[LogEntry] {
+        &self.entries
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// The log as a Markdown document with one section per round.
+    pub fn to_markdown(&self) -> String {
+        let mut markdown = String::from("# Combat Log\n");
+        let mut current_round = None;
+        for entry in &self.entries {
+            if current_round != Some(entry.round) {
+                current_round = Some(entry.round);
+                // Writing to a String can't fail.
+                let _ = write!(markdown, "\n## Round {}\n\n", entry.round);
+            }
+            let _ = writeln!(
+                markdown,
+                "- {} — {}",
+                entry.time.format("%H:%M:%S"),
+                entry.event.describe()
+            );
+        }
+        markdown
+    }
+
+    /// The log as JSON, one object per event.
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(&self.entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn small_log() -> CombatLog {
+        let mut log = CombatLog::default();
+        log.push(
+            1,
+            CombatEvent::TurnStarted {
+                name: "Goblin".to_string(),
+            },
+        );
+        log.push(
+            1,
+            CombatEvent::Damage {
+                target: "Rumi".to_string(),
+                amount: 5,
+                damage_type: None,
+                note: None,
+                hp_after: 15,
+                hp_max: 20,
+            },
+        );
+        log.push(2, CombatEvent::RoundStarted { round: 2 });
+        log
+    }
+
+    #[test]
+    fn markdown_has_a_section_per_round() {
+        let log = small_log();
+        let markdown = log.to_markdown();
+        let lines: Vec<&str> = markdown.lines().filter(|l| !l.is_empty()).collect();
+        assert_eq!(lines[0], "# Combat Log");
+        assert_eq!(lines[1], "## Round 1");
+        let time = log.entries()[0].time.format("%H:%M:%S");
+        assert_eq!(lines[2], format!("- {} — Goblin's turn", time));
+        assert!(lines[3].ends_with(" — Rumi takes 5 damage (15/20 HP)"));
+        assert_eq!(lines[4], "## Round 2");
+        assert!(lines[5].ends_with(" — Round 2 begins"));
+        assert_eq!(lines.len(), 6);
+    }
+
+    #[test]
+    fn json_reads_back_the_same_events() {
+        let log = small_log();
+        let json = log.to_json().unwrap();
+        let entries: Vec<LogEntry> = serde_json::from_str(&json).unwrap();
+        assert_eq!(entries.len(), log.entries().len());
+        for (read, written) in entries.iter().zip(log.entries()) {
+            assert_eq!(read.time, written.time);
+            assert_eq!(read.round, written.round);
+            assert_eq!(read.event.describe(), written.event.describe());
+        }
+    }
+}
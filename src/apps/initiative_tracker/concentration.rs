@@ -0,0 +1,17 @@
+/// The lowest DC a concentration save can have.
+const MIN_SAVE_DC: i32 = 10;
+
+/// The Constitution save DC to keep concentrating after taking `damage`:
+/// 10 or half the damage, whichever is higher.
+pub fn save_dc(damage: i32) -> i32 {
+    MIN_SAVE_DC.max(damage / 2)
+}
+
+/// A concentration save that still has to be made.
+pub struct ConcentrationCheck {
+    /// The concentrating entry.
+    pub id: u64,
+    pub dc: i32,
+    /// The damage that caused it, for the prompt.
+    pub damage: i32,
+}
@@ -0,0 +1,241 @@
+use serde::de::{self, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::fmt;
+
+/// The conditions from the 5e SRD, plus anything the GM types in.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ConditionKind {
+    Blinded,
+    Charmed,
+    Deafened,
+    Exhaustion,
+    Frightened,
+    Grappled,
+    Incapacitated,
+    Invisible,
+    Paralyzed,
+    Petrified,
+    Poisoned,
+    Prone,
+    Restrained,
+    Stunned,
+    Unconscious,
+    Custom(String),
+}
+
+impl ConditionKind {
+    /// Every SRD condition, in the order they are offered in the UI.
+    pub const SRD: [ConditionKind; 15] = [
+        ConditionKind::Blinded,
+        ConditionKind::Charmed,
+        ConditionKind::Deafened,
+        ConditionKind::Exhaustion,
+        ConditionKind::Frightened,
+        ConditionKind::Grappled,
+        ConditionKind::Incapacitated,
+        ConditionKind::Invisible,
+        ConditionKind::Paralyzed,
+        ConditionKind::Petrified,
+        ConditionKind::Poisoned,
+        ConditionKind::Prone,
+        ConditionKind::Restrained,
+        ConditionKind::Stunned,
+        ConditionKind::Unconscious,
+    ];
+
+    pub fn name(&self) -> &str {
+        match self {
+            ConditionKind::Blinded => "Blinded",
+            ConditionKind::Charmed => "Charmed",
+            ConditionKind::Deafened => "Deafened",
+            ConditionKind::Exhaustion => "Exhaustion",
+            ConditionKind::Frightened => "Frightened",
+            ConditionKind::Grappled => "Grappled",
+            ConditionKind::Incapacitated => "Incapacitated",
+            ConditionKind::Invisible => "Invisible",
+            ConditionKind::Paralyzed => "Paralyzed",
+            ConditionKind::Petrified => "Petrified",
+            ConditionKind::Poisoned => "Poisoned",
+            ConditionKind::Prone => "Prone",
+            ConditionKind::Restrained => "Restrained",
+            ConditionKind::Stunned => "Stunned",
+            ConditionKind::Unconscious => "Unconscious",
+            ConditionKind::Custom(name) => name,
+        }
+    }
+
+    /// The SRD condition with this name (ignoring case), or a custom one.
+    pub fn from_name(name: &str) -> Self {
+        let name = name.trim();
+        Self::SRD
+            .iter()
+            .find(|kind| kind.name().eq_ignore_ascii_case(name))
+            .cloned()
+            .unwrap_or_else(|| ConditionKind::Custom(name.to_string()))
+    }
+}
+
+/// Which edge of a turn a condition's duration counts down on.
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TurnBoundary {
+    Start,
+    End,
+}
+
+impl TurnBoundary {
+    pub fn label(self) -> &'static str {
+        match self {
+            TurnBoundary::Start => "start",
+            TurnBoundary::End => "end",
+        }
+    }
+}
+
+/// A condition on a combatant, optionally lasting a number of rounds.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Condition {
+    /// Lets "Previous Turn" find the condition again. Saves without one get a fresh id.
+    #[serde(default = "new_condition_id")]
+    pub id: u64,
+    pub kind: ConditionKind,
+    /// Rounds left; `None` lasts until removed by hand.
+    pub rounds_remaining: Option<u32>,
+    /// The entry whose turns the duration is counted on, e.g. the caster.
+    /// `None` means the affected combatant's own turns.
+    pub source_id: Option<u64>,
+    /// Whether the duration ticks at the start or the end of the source's turn.
+    pub ends_at: TurnBoundary,
+    /// Set when the condition is added during its source's own turn with an
+    /// end-of-turn duration, so that turn's end doesn't count as a full round.
+    #[serde(default)]
+    pub skip_next_tick: bool,
+    /// The entry whose concentration maintains this condition, e.g. the caster
+    /// of Hold Person. It ends when they lose concentration.
+    #[serde(default)]
+    pub concentration_id: Option<u64>,
+}
+
+fn new_condition_id() -> u64 {
+    fastrand::u64(..)
+}
+
+impl Condition {
+    /// A condition that lasts until it is removed by hand.
+    pub fn indefinite(kind: ConditionKind) -> Self {
+        Self {
+            id: new_condition_id(),
+            kind,
+            rounds_remaining: None,
+            source_id: None,
+            ends_at: TurnBoundary::End,
+            skip_next_tick: false,
+            concentration_id: None,
+        }
+    }
+}
+
+/// Reads the condition list, also accepting the comma-separated text that
+/// older saves stored (e.g. "Poisoned, Prone").
+pub fn deserialize_conditions<'de, D>(deserializer: D) -> Result<Vec<Condition>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    // A visitor rather than `#[serde(untagged)]`: untagged enums buffer their
+    // input, which RON (eframe's storage format) can't replay for enums.
+    struct ConditionsVisitor;
+
+    impl<'de> Visitor<'de> for ConditionsVisitor {
+        type Value = Vec<Condition>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a list of conditions or comma-separated condition names")
+        }
+
+        fn visit_str<E: de::Error>(self, text: &str) -> Result<Self::Value, E> {
+            Ok(text
+                .split(',')
+                .filter(|name| !name.trim().is_empty())
+                .map(|name| Condition::indefinite(ConditionKind::from_name(name)))
+                .collect())
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let mut conditions = Vec::new();
+            while let Some(condition) = seq.next_element()? {
+                conditions.push(condition);
+            }
+            Ok(conditions)
+        }
+    }
+
+    deserializer.deserialize_any(ConditionsVisitor)
+}
+
+/// The "add condition" inputs on one track card.
+#[derive(Clone)]
+pub struct ConditionForm {
+    pub kind: ConditionKind,
+    /// The name typed in when `kind` is custom.
+    pub custom_name: String,
+    /// If false, the condition lasts until removed.
+    pub timed: bool,
+    pub rounds: u32,
+    pub source_id: Option<u64>,
+    pub ends_at: TurnBoundary,
+    pub concentration_id: Option<u64>,
+}
+
+impl Default for ConditionForm {
+    fn default() -> Self {
+        Self {
+            kind: ConditionKind::Poisoned,
+            custom_name: String::new(),
+            timed: false,
+            rounds: 1,
+            source_id: None,
+            ends_at: TurnBoundary::End,
+            concentration_id: None,
+        }
+    }
+}
+
+impl ConditionForm {
+    /// The condition described by the form, or `None` if a custom name is missing.
+    /// `concentrating` are the entries concentrating right now; a link to anyone
+    /// else's concentration is dropped, since it could never end the condition.
+    pub fn build(&self, concentrating: &[u64]) -> Option<Condition> {
+        let kind = match &self.kind {
+            ConditionKind::Custom(_) if self.custom_name.trim().is_empty() => return None,
+            ConditionKind::Custom(_) => ConditionKind::from_name(&self.custom_name),
+            kind => kind.clone(),
+        };
+        Some(Condition {
+            id: new_condition_id(),
+            kind,
+            rounds_remaining: self.timed.then_some(self.rounds.max(1)),
+            source_id: self.source_id,
+            ends_at: self.ends_at,
+            skip_next_tick: false,
+            concentration_id: self
+                .concentration_id
+                .filter(|id| concentrating.contains(id)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_drops_a_link_to_ended_concentration() {
+        let form = ConditionForm {
+            concentration_id: Some(7),
+            ..ConditionForm::default()
+        };
+        let linked = form.build(&[7]).unwrap();
+        assert_eq!(linked.concentration_id, Some(7));
+        let unlinked = form.build(&[3]).unwrap();
+        assert_eq!(unlinked.concentration_id, None);
+    }
+}
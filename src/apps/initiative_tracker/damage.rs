@@ -0,0 +1,251 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// The standard 5e damage types.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum DamageType {
+    Acid,
+    Bludgeoning,
+    Cold,
+    Fire,
+    Force,
+    Lightning,
+    Necrotic,
+    Piercing,
+    Poison,
+    Psychic,
+    Radiant,
+    Slashing,
+    Thunder,
+}
+
+impl DamageType {
+    pub const ALL: [DamageType; 13] = [
+        DamageType::Acid,
+        DamageType::Bludgeoning,
+        DamageType::Cold,
+        DamageType::Fire,
+        DamageType::Force,
+        DamageType::Lightning,
+        DamageType::Necrotic,
+        DamageType::Piercing,
+        DamageType::Poison,
+        DamageType::Psychic,
+        DamageType::Radiant,
+        DamageType::Slashing,
+        DamageType::Thunder,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            DamageType::Acid => "acid",
+            DamageType::Bludgeoning => "bludgeoning",
+            DamageType::Cold => "cold",
+            DamageType::Fire => "fire",
+            DamageType::Force => "force",
+            DamageType::Lightning => "lightning",
+            DamageType::Necrotic => "necrotic",
+            DamageType::Piercing => "piercing",
+            DamageType::Poison => "poison",
+            DamageType::Psychic => "psychic",
+            DamageType::Radiant => "radiant",
+            DamageType::Slashing => "slashing",
+            DamageType::Thunder => "thunder",
+        }
+    }
+
+    /// The damage types in stat block text such as "cold; fire", skipping the
+    /// clauses that only hold against some attacks (see [`Self::qualified_clauses`]).
+    pub fn find_in(text: &str) -> Vec<DamageType> {
+        let mut found = Vec::new();
+        for clause in clauses(text).filter(|clause| !is_qualified(clause)) {
+            let clause = clause.to_lowercase();
+            found.extend(
+                Self::ALL
+                    .into_iter()
+                    .filter(|kind| clause.contains(kind.name())),
+            );
+        }
+        found
+    }
+
+    /// The clauses of stat block text that only hold against some attacks, e.g.
+    /// "bludgeoning, piercing, and slashing from nonmagical attacks".
+    pub fn qualified_clauses(text: &str) -> impl Iterator<Item = &str> {
+        clauses(text).filter(|clause| is_qualified(clause))
+    }
+}
+
+/// Stat blocks separate clauses with semicolons, e.g. "cold; bludgeoning, piercing,
+/// and slashing from nonmagical attacks that aren't silvered".
+fn clauses(text: &str) -> impl Iterator<Item = &str> {
+    text.split(';')
+        .map(str::trim)
+        .filter(|clause| !clause.is_empty())
+}
+
+/// Whether a clause limits its damage types to some attacks, as in "from nonmagical
+/// attacks", "that aren't silvered" or "except from magic weapons".
+fn is_qualified(clause: &str) -> bool {
+    let clause = clause.to_lowercase();
+    ["from ", "that ", "except", "while "]
+        .iter()
+        .any(|word| clause.contains(word))
+}
+
+/// A combatant's resistances, immunities and vulnerabilities.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DamageDefenses {
+    pub resistances: BTreeSet<DamageType>,
+    pub immunities: BTreeSet<DamageType>,
+    pub vulnerabilities: BTreeSet<DamageType>,
+    /// Defenses that only hold against some attacks, as written in the stat block,
+    /// e.g. "Resist: bludgeoning, piercing, and slashing from nonmagical attacks".
+    /// The GM applies these by hand.
+    pub qualified: Vec<String>,
+}
+
+/// Damage after defenses, with how it was changed.
+pub struct AdjustedDamage {
+    pub amount: i32,
+    /// e.g. "resisted" or "immune"; `None` if the damage was taken as rolled.
+    pub note: Option<&'static str>,
+}
+
+impl DamageDefenses {
+    /// Applies immunity, resistance (halved, rounded down) and vulnerability (doubled).
+    /// Untyped damage is never adjusted.
+    pub fn adjust(&self, amount: i32, damage_type: Option<DamageType>) -> AdjustedDamage {
+        let Some(damage_type) = damage_type else {
+            return AdjustedDamage { amount, note: None };
+        };
+        if self.immunities.contains(&damage_type) {
+            return AdjustedDamage {
+                amount: 0,
+                note: Some("immune"),
+            };
+        }
+        let resisted = self.resistances.contains(&damage_type);
+        let vulnerable = self.vulnerabilities.contains(&damage_type);
+        // Resistance is applied before vulnerability, as in the PHB.
+        let mut adjusted = amount;
+        if resisted {
+            adjusted /= 2;
+        }
+        if vulnerable {
+            adjusted *= 2;
+        }
+        let note = match (resisted, vulnerable) {
+            (true, true) => Some("resisted and vulnerable"),
+            (true, false) => Some("resisted"),
+            (false, true) => Some("vulnerable"),
+            (false, false) => None,
+        };
+        AdjustedDamage {
+            amount: adjusted,
+            note,
+        }
+    }
+
+    /// A short summary for the track card, e.g. "Resist: fire, cold · Immune: poison".
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        for (label, set) in [
+            ("Resist", &self.resistances),
+            ("Immune", &self.immunities),
+            ("Vuln", &self.vulnerabilities),
+        ] {
+            if !set.is_empty() {
+                let names: Vec<&str> = set.iter().map(|kind| kind.name()).collect();
+                parts.push(format!("{}: {}", label, names.join(", ")));
+            }
+        }
+        parts.join(" · ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_plain_damage_types() {
+        assert_eq!(
+            DamageType::find_in("Cold; Fire"),
+            [DamageType::Cold, DamageType::Fire]
+        );
+        assert!(DamageType::find_in("").is_empty());
+    }
+
+    #[test]
+    fn skips_clauses_limited_to_some_attacks() {
+        let text = "cold; bludgeoning, piercing, and slashing from nonmagical attacks";
+        assert_eq!(DamageType::find_in(text), [DamageType::Cold]);
+        assert_eq!(
+            DamageType::qualified_clauses(text).collect::<Vec<_>>(),
+            ["bludgeoning, piercing, and slashing from nonmagical attacks"]
+        );
+        let silvered =
+            "bludgeoning, piercing, and slashing from nonmagical attacks that aren't silvered";
+        assert!(DamageType::find_in(silvered).is_empty());
+        assert_eq!(DamageType::qualified_clauses(silvered).count(), 1);
+    }
+
+    fn defenses(
+        resistances: &[DamageType],
+        immunities: &[DamageType],
+        vulnerabilities: &[DamageType],
+    ) -> DamageDefenses {
+        DamageDefenses {
+            resistances: resistances.iter().copied().collect(),
+            immunities: immunities.iter().copied().collect(),
+            vulnerabilities: vulnerabilities.iter().copied().collect(),
+            qualified: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn resistance_halves_rounding_down_and_vulnerability_doubles() {
+        let fire = Some(DamageType::Fire);
+        let resisted = defenses(&[DamageType::Fire], &[], &[]).adjust(9, fire);
+        assert_eq!(resisted.amount, 4);
+        assert_eq!(resisted.note, Some("resisted"));
+        let vulnerable = defenses(&[], &[], &[DamageType::Fire]).adjust(9, fire);
+        assert_eq!(vulnerable.amount, 18);
+        assert_eq!(vulnerable.note, Some("vulnerable"));
+        // Halved first, then doubled.
+        let both = defenses(&[DamageType::Fire], &[], &[DamageType::Fire]).adjust(9, fire);
+        assert_eq!(both.amount, 8);
+        assert_eq!(both.note, Some("resisted and vulnerable"));
+    }
+
+    #[test]
+    fn immunity_beats_everything_else() {
+        let all = [DamageType::Poison];
+        let immune = defenses(&all, &all, &all).adjust(12, Some(DamageType::Poison));
+        assert_eq!(immune.amount, 0);
+        assert_eq!(immune.note, Some("immune"));
+    }
+
+    #[test]
+    fn other_and_untyped_damage_is_taken_as_rolled() {
+        let fire_proof = defenses(&[DamageType::Fire], &[DamageType::Fire], &[]);
+        for damage_type in [Some(DamageType::Cold), None] {
+            let adjusted = fire_proof.adjust(7, damage_type);
+            assert_eq!(adjusted.amount, 7);
+            assert_eq!(adjusted.note, None);
+        }
+    }
+
+    #[test]
+    fn summary_lists_each_kind_of_defense() {
+        let defenses = defenses(
+            &[DamageType::Cold, DamageType::Fire],
+            &[DamageType::Poison],
+            &[],
+        );
+        assert_eq!(defenses.summary(), "Resist: cold, fire · Immune: poison");
+        assert_eq!(DamageDefenses::default().summary(), "");
+    }
+}
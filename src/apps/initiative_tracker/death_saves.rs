@@ -0,0 +1,177 @@
+use serde::{Deserialize, Serialize};
+
+/// Successes or failures needed to end the saves.
+const SAVES_TO_RESOLVE: u8 = 3;
+
+/// The outcome of one death saving throw.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DeathSaveResult {
+    Success,
+    Failure,
+    /// A natural 20: the character regains 1 hit point.
+    CriticalSuccess,
+    /// A natural 1: counts as two failures.
+    CriticalFailure,
+}
+
+impl DeathSaveResult {
+    /// Reads a d20 the way the PHB does: 10 or higher succeeds.
+    pub fn from_roll(roll: i32) -> Self {
+        match roll {
+            20 => DeathSaveResult::CriticalSuccess,
+            1 => DeathSaveResult::CriticalFailure,
+            10.. => DeathSaveResult::Success,
+            _ => DeathSaveResult::Failure,
+        }
+    }
+}
+
+/// Death saving throws for a PC at 0 hit points.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DeathSaves {
+    pub successes: u8,
+    pub failures: u8,
+    /// Three successes: unconscious but no longer rolling.
+    pub stable: bool,
+    pub dead: bool,
+}
+
+impl DeathSaves {
+    /// Records a save. On a critical success the caller also restores 1 HP.
+    pub fn record(&mut self, result: DeathSaveResult) {
+        match result {
+            DeathSaveResult::Success => self.add_success(),
+            DeathSaveResult::Failure => self.add_failures(1),
+            DeathSaveResult::CriticalFailure => self.add_failures(2),
+            DeathSaveResult::CriticalSuccess => self.reset(),
+        }
+    }
+
+    fn add_success(&mut self) {
+        self.successes = (self.successes + 1).min(SAVES_TO_RESOLVE);
+        if self.successes == SAVES_TO_RESOLVE {
+            // A stable character stops rolling; the tally starts over if they drop again.
+            self.successes = 0;
+            self.failures = 0;
+            self.stable = true;
+        }
+    }
+
+    /// Adds failed saves, e.g. from a failed roll or taking damage at 0 HP.
+    /// Taking damage also ends stability.
+    pub fn add_failures(&mut self, count: u8) {
+        self.stable = false;
+        self.failures = (self.failures + count).min(SAVES_TO_RESOLVE);
+        if self.failures == SAVES_TO_RESOLVE {
+            self.dead = true;
+        }
+    }
+
+    /// Clears the tally, e.g. when the character regains hit points.
+    pub fn reset(&mut self) {
+        *self = Self {
+            dead: self.dead,
+            ..Self::default()
+        };
+    }
+
+    /// Pips for the track card, e.g. "●●○".
+    pub fn pips(count: u8) -> String {
+        (0..SAVES_TO_RESOLVE)
+            .map(|i| if i < count { '●' } else { '○' })
+            .collect()
+    }
+
+    /// A short status, e.g. "Stable" or "2 successes, 1 failure".
+    pub fn status(&self) -> String {
+        if self.dead {
+            "Dead".to_string()
+        } else if self.stable {
+            "Stable".to_string()
+        } else {
+            format!(
+                "{} success{}, {} failure{}",
+                self.successes,
+                if self.successes == 1 { "" } else { "es" },
+                self.failures,
+                if self.failures == 1 { "" } else { "s" }
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_rolls_like_the_phb() {
+        for (roll, result) in [
+            (1, DeathSaveResult::CriticalFailure),
+            (9, DeathSaveResult::Failure),
+            (10, DeathSaveResult::Success),
+            (20, DeathSaveResult::CriticalSuccess),
+        ] {
+            assert_eq!(DeathSaveResult::from_roll(roll), result);
+        }
+    }
+
+    #[test]
+    fn three_failures_kill() {
+        let mut saves = DeathSaves::default();
+        saves.record(DeathSaveResult::Failure);
+        saves.record(DeathSaveResult::Success);
+        saves.record(DeathSaveResult::Failure);
+        assert!(!saves.dead);
+        saves.record(DeathSaveResult::Failure);
+        assert!(saves.dead);
+        assert_eq!(saves.status(), "Dead");
+    }
+
+    #[test]
+    fn a_natural_1_counts_as_two_failures() {
+        let mut saves = DeathSaves::default();
+        saves.record(DeathSaveResult::CriticalFailure);
+        assert_eq!(saves.failures, 2);
+        assert!(!saves.dead);
+        saves.record(DeathSaveResult::CriticalFailure);
+        assert_eq!(saves.failures, 3);
+        assert!(saves.dead);
+    }
+
+    #[test]
+    fn a_natural_20_clears_the_tally() {
+        let mut saves = DeathSaves::default();
+        saves.record(DeathSaveResult::Failure);
+        saves.record(DeathSaveResult::Success);
+        saves.record(DeathSaveResult::CriticalSuccess);
+        assert_eq!((saves.successes, saves.failures), (0, 0));
+        assert!(!saves.stable && !saves.dead);
+    }
+
+    #[test]
+    fn three_successes_stabilize_until_hit_again() {
+        let mut saves = DeathSaves::default();
+        saves.record(DeathSaveResult::Failure);
+        for _ in 0..3 {
+            saves.record(DeathSaveResult::Success);
+        }
+        assert!(saves.stable);
+        assert_eq!((saves.successes, saves.failures), (0, 0));
+        assert_eq!(saves.status(), "Stable");
+
+        saves.add_failures(2);
+        assert!(!saves.stable);
+        assert_eq!(saves.status(), "0 successes, 2 failures");
+    }
+
+    #[test]
+    fn reset_keeps_the_dead_dead() {
+        let mut saves = DeathSaves::default();
+        saves.add_failures(3);
+        saves.reset();
+        assert!(saves.dead);
+        assert_eq!(saves.failures, 0);
+    }
+}
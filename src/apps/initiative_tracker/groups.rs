@@ -0,0 +1,30 @@
+use serde::{Deserialize, Serialize};
+
+/// Creatures that share one initiative slot and one turn, e.g. a pack of goblins.
+/// The members are ordinary entries with `group_id` set, kept next to each other
+/// in the order; each keeps its own HP and conditions.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct EntryGroup {
+    pub id: u64,
+    pub name: String,
+    /// Whether the members are folded into one line each on the group's card.
+    pub collapsed: bool,
+}
+
+impl EntryGroup {
+    pub fn new(name: &str) -> Self {
+        Self {
+            id: fastrand::u64(..),
+            name: name.to_string(),
+            collapsed: false,
+        }
+    }
+}
+
+/// What the "Group" picker on a track card asks for.
+pub enum GroupChange {
+    Leave,
+    Join(u64),
+    /// Start a new group with just this entry.
+    New,
+}
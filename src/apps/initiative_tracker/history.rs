@@ -0,0 +1,118 @@
+/// How many steps the undo stack keeps.
+const MAX_UNDO_STEPS: usize = 100;
+
+/// Undo/redo stacks of encounter snapshots.
+///
+/// Snapshots are the encounter serialized to JSON, which leaves out form inputs
+/// (they are `#[serde(skip)]`) and makes comparing two states a string compare.
+#[derive(Default)]
+pub struct History {
+    undo: Vec<String>,
+    redo: Vec<String>,
+    /// The state as of the last recorded step.
+    committed: Option<String>,
+}
+
+impl History {
+    /// Records `current` as a new step if it differs from the last one.
+    /// Making a new change throws away anything that could be redone.
+    pub fn record(&mut self, current: String) {
+        match self.committed.take() {
+            Some(previous) if previous != current => {
+                self.undo.push(previous);
+                if self.undo.len() > MAX_UNDO_STEPS {
+                    self.undo.remove(0);
+                }
+                self.redo.clear();
+            }
+            _ => {}
+        }
+        self.committed = Some(current);
+    }
+
+    /// Steps back, returning the state to restore.
+    pub fn undo(&mut self) -> Option<String> {
+        let previous = self.undo.pop()?;
+        if let Some(current) = self.committed.replace(previous.clone()) {
+            self.redo.push(current);
+        }
+        Some(previous)
+    }
+
+    /// Steps forward again after an undo, returning the state to restore.
+    pub fn redo(&mut self) -> Option<String> {
+        let next = self.redo.pop()?;
+        if let Some(current) = self.committed.replace(next.clone()) {
+            self.undo.push(current);
+        }
+        Some(next)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A history that has recorded each of `states` in turn.
+    fn recorded(states: &[&str]) -> History {
+        let mut history = History::default();
+        for state in states {
+            history.record(state.to_string());
+        }
+        history
+    }
+
+    #[test]
+    fn undoes_and_redoes_in_order() {
+        let mut history = recorded(&["a", "b", "c"]);
+        assert_eq!(history.undo().as_deref(), Some("b"));
+        assert_eq!(history.undo().as_deref(), Some("a"));
+        assert_eq!(history.undo(), None);
+        assert_eq!(history.redo().as_deref(), Some("b"));
+        assert_eq!(history.redo().as_deref(), Some("c"));
+        assert_eq!(history.redo(), None);
+        assert_eq!(history.undo().as_deref(), Some("b"));
+    }
+
+    #[test]
+    fn an_unchanged_state_is_not_a_step() {
+        let mut history = recorded(&["a", "a", "b", "b"]);
+        assert_eq!(history.undo().as_deref(), Some("a"));
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn a_new_change_clears_redo() {
+        let mut history = recorded(&["a", "b"]);
+        history.undo();
+        assert!(history.can_redo());
+        history.record("c".to_string());
+        assert!(!history.can_redo());
+        assert_eq!(history.redo(), None);
+        assert_eq!(history.undo().as_deref(), Some("a"));
+    }
+
+    #[test]
+    fn keeps_only_the_latest_steps() {
+        let states: Vec<String> = (0..=MAX_UNDO_STEPS + 5).map(|n| n.to_string()).collect();
+        let mut history = History::default();
+        for state in &states {
+            history.record(state.clone());
+        }
+        let mut undone = Vec::new();
+        while let Some(state) = history.undo() {
+            undone.push(state);
+        }
+        assert_eq!(undone.len(), MAX_UNDO_STEPS);
+        // The oldest steps were dropped first.
+        assert_eq!(undone.last().map(String::as_str), Some("5"));
+    }
+}
@@ -0,0 +1,243 @@
+use eframe::egui::{self, Key, KeyboardShortcut, Modifiers};
+use serde::{Deserialize, Serialize};
+
+/// Something the tracker can do from the keyboard.
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HotkeyAction {
+    NextTurn,
+    PreviousTurn,
+    FocusDamage,
+    AddTrack,
+    Sort,
+    FindCombatant,
+    ShowCheatsheet,
+}
+
+impl HotkeyAction {
+    /// Every action, in the order they are listed in the settings and the cheatsheet.
+    pub const ALL: [HotkeyAction; 7] = [
+        HotkeyAction::NextTurn,
+        HotkeyAction::PreviousTurn,
+        HotkeyAction::FocusDamage,
+        HotkeyAction::AddTrack,
+        HotkeyAction::Sort,
+        HotkeyAction::FindCombatant,
+        HotkeyAction::ShowCheatsheet,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            HotkeyAction::NextTurn => "Next turn",
+            HotkeyAction::PreviousTurn => "Previous turn",
+            HotkeyAction::FocusDamage => "Damage/heal the active combatant",
+            HotkeyAction::AddTrack => "Add a new track",
+            HotkeyAction::Sort => "Sort by initiative",
+            HotkeyAction::FindCombatant => "Jump to a combatant by name",
+            HotkeyAction::ShowCheatsheet => "Show or hide this list",
+        }
+    }
+
+    fn default_shortcut(self) -> KeyboardShortcut {
+        let key = match self {
+            HotkeyAction::NextTurn => Key::N,
+            HotkeyAction::PreviousTurn => Key::P,
+            HotkeyAction::FocusDamage => Key::D,
+            HotkeyAction::AddTrack => Key::A,
+            HotkeyAction::Sort => Key::S,
+            HotkeyAction::FindCombatant => Key::F,
+            HotkeyAction::ShowCheatsheet => Key::F1,
+        };
+        KeyboardShortcut::new(Modifiers::NONE, key)
+    }
+}
+
+/// The key an action is bound to. `None` means the action has no hotkey.
+#[derive(Clone, Serialize, Deserialize)]
+struct Hotkey {
+    action: HotkeyAction,
+    shortcut: Option<KeyboardShortcut>,
+}
+
+/// The rebindable hotkeys. Actions missing from a saved keymap, e.g. ones
+/// added in a later version, use their default key.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Keymap {
+    bindings: Vec<Hotkey>,
+    /// The action waiting for a key press in the settings.
+    #[serde(skip)]
+    rebinding: Option<HotkeyAction>,
+    /// A reserved shortcut that was pressed while rebinding, to explain why it wasn't taken.
+    #[serde(skip)]
+    refused: Option<KeyboardShortcut>,
+}
+
+/// Whether a shortcut is kept for undo and redo: Ctrl+Z, Ctrl+Shift+Z and Ctrl+Y
+/// (Cmd on a Mac).
+fn is_reserved(shortcut: KeyboardShortcut) -> bool {
+    shortcut.modifiers.command && matches!(shortcut.logical_key, Key::Z | Key::Y)
+}
+
+impl Keymap {
+    pub fn shortcut(&self, action: HotkeyAction) -> Option<KeyboardShortcut> {
+        self.bindings
+            .iter()
+            .find(|hotkey| hotkey.action == action)
+            .map_or(Some(action.default_shortcut()), |hotkey| hotkey.shortcut)
+    }
+
+    fn set_shortcut(&mut self, action: HotkeyAction, shortcut: Option<KeyboardShortcut>) {
+        match self
+            .bindings
+            .iter_mut()
+            .find(|hotkey| hotkey.action == action)
+        {
+            Some(hotkey) => hotkey.shortcut = shortcut,
+            None => self.bindings.push(Hotkey { action, shortcut }),
+        }
+    }
+
+    /// Whether the settings are waiting for a new key, which mustn't trigger anything.
+    pub fn is_rebinding(&self) -> bool {
+        self.rebinding.is_some()
+    }
+
+    /// The action whose hotkey was pressed this frame, if any, consuming the key press.
+    pub fn pressed(&self, ctx: &egui::Context) -> Option<HotkeyAction> {
+        let mut bound: Vec<(HotkeyAction, KeyboardShortcut)> = HotkeyAction::ALL
+            .into_iter()
+            .filter_map(|action| Some((action, self.shortcut(action)?)))
+            .collect();
+        // Extra Shift and Alt are ignored when matching, so try Ctrl+Shift+N before Ctrl+N.
+        bound.sort_by_key(|(_, shortcut)| {
+            let m = shortcut.modifiers;
+            std::cmp::Reverse(m.shift as u8 + m.alt as u8 + m.command as u8)
+        });
+        ctx.input_mut(|i| {
+            bound
+                .into_iter()
+                .find(|(_, shortcut)| i.consume_shortcut(shortcut))
+                .map(|(action, _)| action)
+        })
+    }
+
+    /// The hotkey for an action as text, e.g. for a button's hover text.
+    pub fn describe(&self, ctx: &egui::Context, action: HotkeyAction) -> String {
+        self.shortcut(action)
+            .map_or_else(|| "No hotkey".to_string(), |s| ctx.format_shortcut(&s))
+    }
+
+    /// The keymap section of the settings window.
+    pub fn settings_ui(&mut self, ui: &mut egui::Ui) {
+        if let Some(action) = self.rebinding {
+            // Take the key press, so that binding e.g. Delete doesn't also act on it.
+            let pressed = ui.input_mut(|i| {
+                let shortcut = i.events.iter().find_map(|event| match event {
+                    egui::Event::Key {
+                        key,
+                        pressed: true,
+                        modifiers,
+                        ..
+                    } => Some(KeyboardShortcut::new(*modifiers, *key)),
+                    _ => None,
+                })?;
+                i.consume_key(shortcut.modifiers, shortcut.logical_key);
+                Some(shortcut)
+            });
+            match pressed {
+                Some(shortcut) if shortcut.logical_key == Key::Escape => {
+                    self.rebinding = None;
+                    self.refused = None;
+                }
+                Some(shortcut) if is_reserved(shortcut) => self.refused = Some(shortcut),
+                Some(shortcut) => {
+                    // One key does one thing: take it from whatever had it before.
+                    for other in HotkeyAction::ALL {
+                        if self.shortcut(other) == Some(shortcut) {
+                            self.set_shortcut(other, None);
+                        }
+                    }
+                    self.set_shortcut(action, Some(shortcut));
+                    self.rebinding = None;
+                    self.refused = None;
+                }
+                None => {}
+            }
+        }
+
+        egui::Grid::new("hotkey_settings_grid")
+            .num_columns(3)
+            .spacing([10.0, 4.0])
+            .show(ui, |ui| {
+                for action in HotkeyAction::ALL {
+                    ui.label(action.label());
+                    if self.rebinding == Some(action) {
+                        match self.refused {
+                            Some(shortcut) => ui.colored_label(
+                                ui.visuals().warn_fg_color,
+                                format!(
+                                    "{} is kept for undo and redo; press another key",
+                                    ui.ctx().format_shortcut(&shortcut)
+                                ),
+                            ),
+                            None => ui.label("Press a key (Esc cancels)"),
+                        };
+                    } else if ui.button(self.describe(ui.ctx(), action)).clicked() {
+                        self.rebinding = Some(action);
+                    }
+                    if ui
+                        .add_enabled(self.shortcut(action).is_some(), egui::Button::new("X"))
+                        .on_hover_text("Remove this hotkey")
+                        .clicked()
+                    {
+                        self.set_shortcut(action, None);
+                    }
+                    ui.end_row();
+                }
+            });
+        if ui.button("Reset to Defaults").clicked() {
+            self.bindings.clear();
+            self.rebinding = None;
+            self.refused = None;
+        }
+    }
+
+    /// Every action with its hotkey.
+    pub fn cheatsheet_ui(&self, ui: &mut egui::Ui) {
+        egui::Grid::new("hotkey_cheatsheet_grid")
+            .num_columns(2)
+            .spacing([20.0, 4.0])
+            .striped(true)
+            .show(ui, |ui| {
+                for action in HotkeyAction::ALL {
+                    ui.strong(self.describe(ui.ctx(), action));
+                    ui.label(action.label());
+                    ui.end_row();
+                }
+            });
+        ui.weak("Hotkeys are ignored while typing in a field. Change them in Settings.");
+    }
+}
+
+/// Which field of a track card should get keyboard focus.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum CardField {
+    Name,
+    Damage,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn undo_and_redo_shortcuts_are_reserved() {
+        let ctrl = Modifiers::COMMAND;
+        let ctrl_shift = ctrl | Modifiers::SHIFT;
+        assert!(is_reserved(KeyboardShortcut::new(ctrl, Key::Z)));
+        assert!(is_reserved(KeyboardShortcut::new(ctrl_shift, Key::Z)));
+        assert!(is_reserved(KeyboardShortcut::new(ctrl, Key::Y)));
+        assert!(!is_reserved(KeyboardShortcut::new(Modifiers::NONE, Key::Z)));
+        assert!(!is_reserved(KeyboardShortcut::new(ctrl, Key::N)));
+    }
+}
@@ -0,0 +1,4057 @@
+mod bulk_add;
+mod combat_log;
+mod concentration;
+mod conditions;
+mod damage;
+mod death_saves;
+mod groups;
+mod history;
+mod hotkeys;
+mod player_view;
+mod turn_steps;
+
+use crate::apps::bestiary::StatBlock;
+use crate::dice::{DiceExpr, RollMode};
+use bulk_add::BulkAddForm;
+use combat_log::{CombatEvent, CombatLog};
+use concentration::ConcentrationCheck;
+use conditions::{Condition, ConditionForm, ConditionKind, TurnBoundary, deserialize_conditions};
+use damage::{DamageDefenses, DamageType};
+use death_saves::{DeathSaveResult, DeathSaves};
+use eframe::egui;
+use groups::{EntryGroup, GroupChange};
+use history::History;
+use hotkeys::{CardField, HotkeyAction, Keymap};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap};
+use std::error::Error;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use turn_steps::{ConditionTick, DelayMove, TickEffect, TurnStartReset, TurnSteps};
+
+/// Key under which the tracker state is kept in eframe's persistent storage.
+const STORAGE_KEY: &str = "initiative_tracker";
+
+/// Version of the saved tracker layout.
+/// Bump this when a change can't be handled by `#[serde(default)]` alone.
+const SAVE_VERSION: u32 = 1;
+
+/// How many recently used encounter files to remember.
+const MAX_RECENT_ENCOUNTERS: usize = 8;
+
+/// Lair actions happen on initiative count 20.
+const LAIR_INITIATIVE: i32 = 20;
+
+/// Party roster that ships with the app, copied into the config dir on first run.
+const BUNDLED_PARTY: &str = include_str!("../../../resources/dnd_pc_info.json");
+
+/// One PC in a party roster file.
+/// Only `name` and `hp` are required, so rosters written before the other stats existed still load.
+#[derive(Deserialize)]
+struct PCInfo {
+    name: String,
+    /// Current hit points.
+    hp: i32,
+    /// Maximum hit points; the same as `hp` when missing.
+    #[serde(default)]
+    max_hp: Option<i32>,
+    #[serde(default)]
+    temp_hp: i32,
+    #[serde(default = "default_armor_class")]
+    ac: i32,
+    #[serde(default)]
+    initiative_modifier: i32,
+    #[serde(default = "default_passive_perception")]
+    passive_perception: i32,
+}
+
+// Unarmored AC with no Dexterity bonus.
+fn default_armor_class() -> i32 {
+    10
+}
+
+// Passive Perception with no Wisdom bonus or proficiency.
+fn default_passive_perception() -> i32 {
+    10
+}
+
+fn load_pcs_from_file(path: &Path) -> Result<Vec<PCInfo>, Box<dyn Error>> {
+    // 1. Read the file's contents into a string.
+    let data_string = std::fs::read_to_string(path)?;
+
+    // 2. Parse the string of JSON data into a Vec<InitiativeTrackerEntry>.
+    //    The `?` operator will automatically handle any parsing errors.
+    let entries = serde_json::from_str(&data_string)?;
+
+    // 3. Return the successfully parsed data.
+    Ok(entries)
+}
+
+/// Where the party roster lives by default: `<config dir>/ttrpg_app/dnd_pc_info.json`
+/// (e.g. `~/.config` on Linux, `%APPDATA%` on Windows). "Add PCs" writes the
+/// bundled roster there if that file hasn't been created yet.
+fn default_party_path() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_default()
+        .join("ttrpg_app")
+        .join("dnd_pc_info.json")
+}
+
+/// Folder the encounter file dialogs open in by default: `<config dir>/ttrpg_app/encounters`.
+fn default_encounter_dir() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_default()
+        .join("ttrpg_app")
+        .join("encounters")
+}
+
+fn write_bundled_party(path: &Path) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, BUNDLED_PARTY)
+}
+
+/// A named party roster that "Add PCs" can load.
+#[derive(Clone, Serialize, Deserialize)]
+struct PartyFile {
+    name: String,
+    path: PathBuf,
+}
+
+/// A way to order combatants with the same initiative before asking the GM.
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+enum TieBreakRule {
+    /// The higher initiative modifier (usually Dexterity) goes first.
+    HigherModifier,
+    /// Player characters go before monsters.
+    PcsFirst,
+    /// Tied combatants roll a d20 and the higher roll goes first.
+    RollOff,
+}
+
+impl TieBreakRule {
+    fn label(self) -> &'static str {
+        match self {
+            TieBreakRule::HigherModifier => "Higher initiative modifier wins",
+            TieBreakRule::PcsFirst => "PCs before monsters",
+            TieBreakRule::RollOff => "Roll-off (d20)",
+        }
+    }
+}
+
+/// A tie-break rule and whether it is switched on.
+#[derive(Clone, Serialize, Deserialize)]
+struct TieBreakSetting {
+    rule: TieBreakRule,
+    enabled: bool,
+}
+
+/// Orders two entries for the turn order: initiative first, then each enabled
+/// tie-break rule in turn. `Ordering::Less` means `a` acts before `b`.
+fn compare_turn_order(
+    a: &InitiativeTrackerEntry,
+    b: &InitiativeTrackerEntry,
+    rules: &[TieBreakSetting],
+) -> Ordering {
+    // Lair actions lose initiative ties.
+    // The order left by a delay beats the rules; entries without one go after.
+    let mut ordering = b
+        .initiative
+        .cmp(&a.initiative)
+        .then_with(|| a.lair_actions.cmp(&b.lair_actions))
+        .then_with(|| match (a.delay_rank, b.delay_rank) {
+            (Some(a_rank), Some(b_rank)) => a_rank.cmp(&b_rank),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        });
+    for setting in rules.iter().filter(|setting| setting.enabled) {
+        ordering = ordering.then_with(|| match setting.rule {
+            TieBreakRule::HigherModifier => b.initiative_modifier.cmp(&a.initiative_modifier),
+            TieBreakRule::PcsFirst => b.is_pc.cmp(&a.is_pc),
+            TieBreakRule::RollOff => b.roll_off.cmp(&a.roll_off),
+        });
+    }
+    ordering
+}
+
+/// Orders entries the rules consider tied by the rank chosen in the tie-breaker.
+/// Entries without a rank (e.g. late joiners) go after the ranked ones.
+fn compare_tie_break_rank(a: &InitiativeTrackerEntry, b: &InitiativeTrackerEntry) -> Ordering {
+    match (a.tie_break_rank, b.tie_break_rank) {
+        (Some(a_rank), Some(b_rank)) => a_rank.cmp(&b_rank),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+/// User preferences for the tracker, kept separately from the encounter itself.
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+struct TrackerSettings {
+    /// All saved party rosters.
+    parties: Vec<PartyFile>,
+    /// Index into `parties` of the roster "Add PCs" loads.
+    active_party: usize,
+    /// Tie-break rules tried in order by "Sort by Initiative".
+    tie_break_rules: Vec<TieBreakSetting>,
+    /// Keyboard shortcuts for running the tracker without the mouse.
+    keymap: Keymap,
+}
+
+impl Default for TrackerSettings {
+    fn default() -> Self {
+        Self {
+            parties: vec![PartyFile {
+                name: "Default Party".to_string(),
+                path: default_party_path(),
+            }],
+            active_party: 0,
+            tie_break_rules: vec![
+                TieBreakSetting {
+                    rule: TieBreakRule::HigherModifier,
+                    enabled: true,
+                },
+                TieBreakSetting {
+                    rule: TieBreakRule::PcsFirst,
+                    enabled: false,
+                },
+                TieBreakSetting {
+                    rule: TieBreakRule::RollOff,
+                    enabled: false,
+                },
+            ],
+            keymap: Keymap::default(),
+        }
+    }
+}
+
+impl TrackerSettings {
+    /// The roster "Add PCs" should load, if any party is configured.
+    fn active_party(&self) -> Option<&PartyFile> {
+        self.parties.get(self.active_party)
+    }
+
+    /// The contents of the settings window.
+    fn settings_ui(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Parties");
+        ui.label("The selected party is added by \"Add PCs\".");
+        let mut index_to_remove: Option<usize> = None;
+        egui::Grid::new("party_settings_grid")
+            .num_columns(4)
+            .spacing([10.0, 4.0])
+            .show(ui, |ui| {
+                for (i, party) in self.parties.iter_mut().enumerate() {
+                    ui.radio_value(&mut self.active_party, i, "");
+                    ui.add(egui::TextEdit::singleline(&mut party.name).desired_width(120.0));
+                    ui.horizontal(|ui| {
+                        ui.label(party.path.display().to_string());
+                        if ui.button("Browse...").clicked() {
+                            let mut dialog = rfd::FileDialog::new().add_filter("Party", &["json"]);
+                            if let Some(parent) = party.path.parent() {
+                                dialog = dialog.set_directory(parent);
+                            }
+                            if let Some(path) = dialog.pick_file() {
+                                party.path = path;
+                            }
+                        }
+                    });
+                    if ui.button("X").on_hover_text("Forget this party").clicked() {
+                        index_to_remove = Some(i);
+                    }
+                    ui.end_row();
+                }
+            });
+        if let Some(index) = index_to_remove {
+            self.parties.remove(index);
+            // Keep the selection on the same party, or the last one if it was removed.
+            if self.active_party > index || self.active_party >= self.parties.len() {
+                self.active_party = self.active_party.saturating_sub(1);
+            }
+        }
+        if ui.button("Add Party").clicked()
+            && let Some(path) = rfd::FileDialog::new()
+                .add_filter("Party", &["json"])
+                .pick_file()
+        {
+            let name = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "New Party".to_string());
+            self.parties.push(PartyFile { name, path });
+        }
+
+        ui.separator();
+        ui.heading("Tie Breaking");
+        ui.label("Applied from top to bottom when sorting. Ties left over are resolved by hand.");
+        let mut swap_rules: Option<(usize, usize)> = None;
+        let rule_count = self.tie_break_rules.len();
+        for (i, setting) in self.tie_break_rules.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                if ui.add_enabled(i > 0, egui::Button::new("^")).clicked() {
+                    swap_rules = Some((i, i - 1));
+                }
+                if ui
+                    .add_enabled(i + 1 < rule_count, egui::Button::new("v"))
+                    .clicked()
+                {
+                    swap_rules = Some((i, i + 1));
+                }
+                ui.checkbox(&mut setting.enabled, setting.rule.label());
+            });
+        }
+        if let Some((a, b)) = swap_rules {
+            self.tie_break_rules.swap(a, b);
+        }
+
+        ui.separator();
+        ui.heading("Hotkeys");
+        ui.label("Click a hotkey and press the new key to change it.");
+        self.keymap.settings_ui(ui);
+    }
+}
+
+/// A prepared encounter as it is written to a named JSON file.
+#[derive(Serialize, Deserialize)]
+struct EncounterFile {
+    version: u32,
+    entries: Vec<InitiativeTrackerEntry>,
+    #[serde(default)]
+    groups: Vec<EntryGroup>,
+}
+
+fn save_encounter_to_file(
+    path: &Path,
+    entries: &[InitiativeTrackerEntry],
+    groups: &[EntryGroup],
+) -> Result<(), Box<dyn Error>> {
+    // Make sure the folder exists, e.g. on the first save into a new folder.
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let file = EncounterFile {
+        version: SAVE_VERSION,
+        entries: entries.to_vec(),
+        groups: groups.to_vec(),
+    };
+    std::fs::write(path, serde_json::to_string_pretty(&file)?)?;
+    Ok(())
+}
+
+fn load_encounter_from_file(path: &Path) -> Result<EncounterFile, Box<dyn Error>> {
+    let data_string = std::fs::read_to_string(path)?;
+    let file: EncounterFile = serde_json::from_str(&data_string)?;
+    if file.version > SAVE_VERSION {
+        return Err(format!(
+            "encounter was saved by a newer version ({} > {})",
+            file.version, SAVE_VERSION
+        )
+        .into());
+    }
+    Ok(file)
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)] // Missing fields in older saves fall back to `Default`
+pub struct InitiativeTrackerEntry {
+    /// Identifies the combatant independently of its position in the list.
+    id: u64,
+    name: String,
+    initiative: i32,
+    /// Order among entries that are tied on initiative and every tie-break rule.
+    /// Set when a tie is resolved, so the chosen order survives re-sorting.
+    tie_break_rank: Option<u32>,
+    /// This entry's d20 for the roll-off tie-break rule, kept so re-sorting doesn't re-roll it.
+    roll_off: Option<i32>,
+    /// Order among entries on the same initiative after someone delayed behind one of
+    /// them. Unlike `tie_break_rank` it goes before the tie-break rules, so re-sorting
+    /// can't put the delayer back in front.
+    delay_rank: Option<u32>,
+    hp_current: i32,
+    hp_total: i32,
+    /// Temporary hit points, lost before `hp_current`.
+    hp_temp: i32,
+    /// How far `hp_total` is currently lowered, e.g. by Life Drain.
+    hp_max_reduction: i32,
+    armor_class: i32,
+    initiative_modifier: i32,
+    passive_perception: i32,
+    /// Damage types this combatant resists, ignores or is vulnerable to.
+    defenses: DamageDefenses,
+    /// True for player characters, whose players usually roll their own initiative
+    /// and who make death saves at 0 HP.
+    is_pc: bool,
+    death_saves: DeathSaves,
+    /// Left out of the Player View until revealed, e.g. for ambushers.
+    hidden: bool,
+    /// Loses its turn in the first round.
+    surprised: bool,
+    /// Delayed this round: their turn already started and ended, so when it comes
+    /// up again they just act, without another start or end of turn.
+    delaying: bool,
+    /// The group this entry takes its turn with, if any.
+    group_id: Option<u64>,
+    /// Marks the lair itself: a turn on initiative count 20 with no stats of its own.
+    lair_actions: bool,
+    /// The trigger of an action held with Ready, until it's used or the entry's next turn.
+    readied_action: Option<String>,
+    /// The trigger being typed in for a new readied action.
+    #[serde(skip)]
+    ready_form: String,
+    /// Legendary actions per round; 0 for creatures that have none.
+    legendary_actions: u32,
+    /// Legendary actions left until this creature's next turn.
+    legendary_actions_left: u32,
+    /// Whether this combatant is concentrating on a spell.
+    concentrating: bool,
+    /// The spell being concentrated on; may be left blank.
+    concentration_spell: String,
+    /// Added to concentration saves, i.e. the Constitution save bonus.
+    con_save_modifier: i32,
+    /// Whether this combatant rolls initiative with advantage or disadvantage.
+    initiative_roll_mode: RollMode,
+    #[serde(skip)] // Form input, not part of the encounter
+    hp_update: i32,
+    #[serde(skip)]
+    hp_update_kind: HpUpdateKind,
+    /// The type of the damage in the "Damage/Heal" row; `None` is untyped.
+    #[serde(skip)]
+    hp_update_damage_type: Option<DamageType>,
+    /// Whether the damage in the "Damage/Heal" row is from a critical hit.
+    #[serde(skip)]
+    hp_update_critical: bool,
+    /// What the last "Update" did, e.g. "Took 7 fire (resisted, 14 rolled)".
+    #[serde(skip)]
+    last_hp_update: Option<String>,
+    #[serde(deserialize_with = "deserialize_conditions")]
+    conditions: Vec<Condition>,
+    #[serde(skip)]
+    condition_form: ConditionForm,
+}
+
+// Add a default impl for InitiativeTrackerEntry to make adding new ones easier
+impl Default for InitiativeTrackerEntry {
+    fn default() -> Self {
+        Self {
+            id: fastrand::u64(..), // Random ids don't need a counter carried between sessions
+            name: "New Combatant".to_string(),
+            initiative: 0,
+            tie_break_rank: None,
+            roll_off: None,
+            delay_rank: None,
+            hp_current: 10,
+            hp_total: 10,
+            hp_temp: 0,
+            hp_max_reduction: 0,
+            armor_class: default_armor_class(),
+            initiative_modifier: 0,
+            passive_perception: default_passive_perception(),
+            defenses: DamageDefenses::default(),
+            is_pc: false,
+            death_saves: DeathSaves::default(),
+            hidden: false,
+            surprised: false,
+            delaying: false,
+            group_id: None,
+            lair_actions: false,
+            readied_action: None,
+            ready_form: String::new(),
+            legendary_actions: 0,
+            legendary_actions_left: 0,
+            concentrating: false,
+            concentration_spell: String::new(),
+            con_save_modifier: 0,
+            initiative_roll_mode: RollMode::Normal,
+            hp_update: 0,
+            hp_update_kind: HpUpdateKind::Damage,
+            hp_update_damage_type: None,
+            hp_update_critical: false,
+            last_hp_update: None,
+            conditions: Vec::new(),
+            condition_form: ConditionForm::default(),
+        }
+    }
+}
+
+/// What the "Damage/Heal" row does with its amount.
+#[derive(Clone, Copy, PartialEq, Default)]
+enum HpUpdateKind {
+    #[default]
+    Damage,
+    Heal,
+    /// Grant temporary hit points.
+    TempHp,
+}
+
+impl InitiativeTrackerEntry {
+    /// Maximum hit points after any reduction.
+    fn hp_max(&self) -> i32 {
+        (self.hp_total - self.hp_max_reduction).max(0)
+    }
+
+    /// Takes damage, using up temporary hit points first.
+    /// A PC at 0 HP fails a death save (two on a critical hit), and damage that
+    /// reaches their max HP past 0 kills them outright.
+    fn take_damage(&mut self, amount: i32, critical: bool) {
+        let absorbed = amount.min(self.hp_temp);
+        self.hp_temp -= absorbed;
+        let damage = amount - absorbed;
+        if damage <= 0 {
+            return;
+        }
+        if self.is_pc && !self.death_saves.dead {
+            let overflow = damage - self.hp_current;
+            if overflow >= self.hp_max() {
+                self.death_saves.dead = true;
+            } else if self.hp_current == 0 {
+                self.death_saves.add_failures(if critical { 2 } else { 1 });
+            }
+        }
+        self.hp_current = (self.hp_current - damage).max(0);
+    }
+
+    /// Regains hit points up to the (possibly reduced) maximum.
+    /// Temporary hit points can't be healed, and the dead stay dead.
+    fn heal(&mut self, amount: i32) {
+        if self.death_saves.dead || amount <= 0 {
+            return;
+        }
+        if self.hp_current == 0 {
+            self.death_saves.reset();
+        }
+        self.hp_current = (self.hp_current + amount).min(self.hp_max());
+    }
+
+    /// A PC at 0 HP who still has to make death saves.
+    fn is_dying(&self) -> bool {
+        self.is_pc && self.hp_current == 0 && !self.death_saves.stable && !self.death_saves.dead
+    }
+
+    /// Temporary hit points don't stack: keep whichever pool is larger.
+    fn grant_temp_hp(&mut self, amount: i32) {
+        self.hp_temp = self.hp_temp.max(amount);
+    }
+
+    /// A creature from the bestiary, with `hp` either rolled or the average.
+    pub fn from_stat_block(block: &StatBlock, hp: i32) -> Self {
+        let mut defenses = DamageDefenses::default();
+        for (label, set, texts) in [
+            (
+                "Resist",
+                &mut defenses.resistances,
+                &block.damage_resistances,
+            ),
+            ("Immune", &mut defenses.immunities, &block.damage_immunities),
+            (
+                "Vuln",
+                &mut defenses.vulnerabilities,
+                &block.damage_vulnerabilities,
+            ),
+        ] {
+            for text in texts {
+                set.extend(DamageType::find_in(text));
+                defenses.qualified.extend(
+                    DamageType::qualified_clauses(text).map(|clause| format!("{label}: {clause}")),
+                );
+            }
+        }
+        let legendary_actions = block.legendary_actions_per_round();
+        Self {
+            name: block.name.clone(),
+            hp_current: hp,
+            hp_total: hp,
+            armor_class: block.armor_class,
+            initiative_modifier: StatBlock::modifier(block.dexterity),
+            passive_perception: block.passive_perception(),
+            con_save_modifier: StatBlock::modifier(block.constitution),
+            defenses,
+            legendary_actions,
+            legendary_actions_left: legendary_actions,
+            ..Self::default()
+        }
+    }
+
+    /// The lair's own turn in the order.
+    fn lair() -> Self {
+        Self {
+            name: "Lair Actions".to_string(),
+            initiative: LAIR_INITIATIVE,
+            lair_actions: true,
+            hp_current: 0,
+            hp_total: 0,
+            ..Self::default()
+        }
+    }
+
+    /// Forgets how this entry was placed among ties, e.g. after its initiative changes.
+    fn clear_tie_break(&mut self) {
+        self.tie_break_rank = None;
+        self.roll_off = None;
+        self.delay_rank = None;
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct InitiativeTracker {
+    /// The list of all combatants in the tracker.
+    entries: Vec<InitiativeTrackerEntry>,
+    /// The index of the active combatant in the `entries` vector.
+    /// `None` means combat has not started.
+    active_index: Option<usize>,
+    /// The current round number.
+    round_count: u32,
+    /// If true, the tie-breaker pop-up window should be displayed.
+    show_tie_breaker: bool,
+    /// Ids of entries the tie-break rules couldn't separate, one list per tie,
+    /// still to be ordered by hand.
+    tie_groups: Vec<Vec<u64>>,
+    /// The encounter file that "Save" writes to, if one has been opened or saved.
+    encounter_path: Option<PathBuf>,
+    /// Recently opened or saved encounter files, most recent first.
+    recent_encounters: Vec<PathBuf>,
+    /// User preferences such as the party rosters.
+    settings: TrackerSettings,
+    /// What has happened so far, for session recaps.
+    combat_log: CombatLog,
+    /// Messages for the GM, e.g. conditions that just expired.
+    #[serde(skip)]
+    notices: Vec<String>,
+    /// If true, the settings window is open.
+    #[serde(skip)]
+    show_settings: bool,
+    /// Groups of entries that share a turn.
+    groups: Vec<EntryGroup>,
+    /// The turn changes "Previous Turn" can step back over.
+    turn_steps: TurnSteps,
+    /// Whether the Player View window is open.
+    #[serde(skip)]
+    show_player_view: bool,
+    #[serde(skip)]
+    show_bulk_add: bool,
+    #[serde(skip)]
+    bulk_add: BulkAddForm,
+    /// The dying PC whose turn just started and who still has to roll a death save.
+    #[serde(skip)]
+    death_save_prompt: Option<u64>,
+    /// Concentration saves owed from damage, oldest first.
+    #[serde(skip)]
+    concentration_checks: Vec<ConcentrationCheck>,
+    /// Random number generator for initiative and death save rolls.
+    #[serde(skip)]
+    rng: fastrand::Rng,
+    /// Undo/redo steps for this session.
+    #[serde(skip)]
+    history: History,
+    /// Whether the last undo step still matches the fight. Cleared by input that
+    /// may have changed it, so quiet frames don't pay for a snapshot.
+    #[serde(skip)]
+    history_up_to_date: bool,
+    /// Whether the hotkey cheatsheet is open.
+    #[serde(skip)]
+    show_cheatsheet: bool,
+    /// What has been typed into "Jump to Combatant", while it is open.
+    #[serde(skip)]
+    find_query: Option<String>,
+    /// The card field to give keyboard focus to on the next frame.
+    #[serde(skip)]
+    card_focus: Option<(u64, CardField)>,
+}
+
+// "We are now starting an implementation block..."
+impl Default for InitiativeTracker {
+    // "...where we will implement the 'Default' trait for the 'InitiativeTracker' struct."
+    // "Here is the 'default' function that the 'Default' trait requires."
+    // "It takes no arguments and returns an instance of 'Self' (which is shorthand for InitiativeTracker)."
+    fn default() -> Self {
+        // "We are creating and returning a new instance of 'Self' (InitiativeTracker)."
+        Self {
+            // "Initialize each field of the struct with its own default value."
+            entries: Vec::new(),     // The default for a Vec is an empty vector.
+            active_index: None, // The default for our optional index is None (nothing is active).
+            round_count: 1,     // We decided a sensible default for the round count is 1.
+            show_tie_breaker: false, // The default is to not show the pop-up.
+            tie_groups: Vec::new(), // The default is no unresolved ties.
+            encounter_path: None, // No encounter file until one is saved or opened.
+            recent_encounters: Vec::new(),
+            settings: TrackerSettings::default(),
+            combat_log: CombatLog::default(),
+            notices: Vec::new(),
+            show_settings: false,
+            groups: Vec::new(),
+            turn_steps: TurnSteps::default(),
+            show_player_view: false,
+            show_bulk_add: false,
+            bulk_add: BulkAddForm::default(),
+            death_save_prompt: None,
+            concentration_checks: Vec::new(),
+            rng: fastrand::Rng::new(),
+            history: History::default(),
+            history_up_to_date: false,
+            show_cheatsheet: false,
+            find_query: None,
+            card_focus: None,
+        }
+    }
+}
+
+/// The fight itself, as undo/redo snapshots it: everything but settings, file bookkeeping
+/// and the combat log. The log only grows, so an undone step stays on record, and leaving
+/// it out keeps each snapshot from growing with the length of the fight.
+#[derive(Deserialize)]
+struct EncounterState {
+    entries: Vec<InitiativeTrackerEntry>,
+    active_index: Option<usize>,
+    round_count: u32,
+    show_tie_breaker: bool,
+    tie_groups: Vec<Vec<u64>>,
+    #[serde(default)]
+    groups: Vec<EntryGroup>,
+    #[serde(default)]
+    turn_steps: TurnSteps,
+}
+
+/// A borrowing twin of `EncounterState` for taking snapshots without cloning.
+#[derive(Serialize)]
+struct EncounterStateRef<'a> {
+    entries: &'a [InitiativeTrackerEntry],
+    active_index: Option<usize>,
+    round_count: u32,
+    show_tie_breaker: bool,
+    tie_groups: &'a [Vec<u64>],
+    groups: &'a [EntryGroup],
+    turn_steps: &'a TurnSteps,
+}
+
+/// The tracker as it is written to storage, tagged with the layout version.
+#[derive(Serialize, Deserialize)]
+struct SavedTracker {
+    version: u32,
+    tracker: InitiativeTracker,
+}
+
+// "Now we are implementing the 'App' trait for our 'InitiativeTracker' struct."
+impl InitiativeTracker {
+    /// Restores the tracker from eframe's storage.
+    /// Returns `None` if nothing was saved or the save was written by a newer version.
+    pub fn load_state(storage: &dyn eframe::Storage) -> Option<Self> {
+        let saved: SavedTracker = eframe::get_value(storage, STORAGE_KEY)?;
+        if saved.version > SAVE_VERSION {
+            eprintln!(
+                "Ignoring saved encounter from a newer version ({} > {})",
+                saved.version, SAVE_VERSION
+            );
+            return None;
+        }
+        Some(saved.tracker)
+    }
+
+    /// Sorts the entries by initiative, highest first, using the tie-break rules
+    /// from the settings and then any order chosen in the tie-breaker.
+    /// Ties that are still open are queued for the tie-breaker.
+    fn sort_by_initiative(&mut self) {
+        // Roll any missing roll-offs up front so the comparisons stay consistent while sorting.
+        if self
+            .settings
+            .tie_break_rules
+            .iter()
+            .any(|setting| setting.enabled && setting.rule == TieBreakRule::RollOff)
+        {
+            for entry in self.entries.iter_mut().filter(|e| e.roll_off.is_none()) {
+                entry.roll_off = Some(self.rng.i32(1..=20));
+            }
+        }
+        self.sync_groups();
+        self.order_entries();
+
+        // Walk each run of turns the rules can't separate. A run whose order
+        // hasn't been settled (a missing or repeated rank) goes to the tie-breaker.
+        // A group takes part through its first member.
+        self.tie_groups.clear();
+        let turns = self.turn_starts();
+        let mut run_start = 0;
+        for k in 1..=turns.len() {
+            let tied_with_previous = k < turns.len()
+                && compare_turn_order(
+                    &self.entries[turns[k - 1]],
+                    &self.entries[turns[k]],
+                    &self.settings.tie_break_rules,
+                ) == Ordering::Equal;
+            if tied_with_previous {
+                continue;
+            }
+            let run = &turns[run_start..k];
+            let settled = run.windows(2).all(|pair| {
+                matches!(
+                    (self.entries[pair[0]].tie_break_rank, self.entries[pair[1]].tie_break_rank),
+                    (Some(a), Some(b)) if a < b
+                )
+            });
+            if run.len() > 1 && !settled {
+                // Start from the current order; the tie-breaker edits these ranks.
+                for (rank, &i) in run.iter().enumerate() {
+                    self.entries[i].tie_break_rank = Some(rank as u32);
+                }
+                self.tie_groups
+                    .push(run.iter().map(|&i| self.entries[i].id).collect());
+            }
+            run_start = k;
+        }
+        self.sync_groups();
+
+        // Mid-fight the turn stays with whoever had it (`order_entries` followed them),
+        // so a re-sort neither restarts combat nor loses the "Previous Turn" steps.
+        self.show_tie_breaker = !self.tie_groups.is_empty();
+    }
+
+    /// Puts the entries in turn order, keeping each group's members together
+    /// (in their current order) and the active turn on the same creature.
+    fn order_entries(&mut self) {
+        let rules = &self.settings.tie_break_rules;
+        let leaders = self.group_leaders();
+        let entries = &self.entries;
+        let mut order: Vec<usize> = (0..entries.len()).collect();
+        // A stable sort on each group's first member keeps the members in place.
+        order.sort_by(|&a, &b| {
+            let (leader_a, leader_b) = (&entries[leaders[a]], &entries[leaders[b]]);
+            compare_turn_order(leader_a, leader_b, rules)
+                .then_with(|| compare_tie_break_rank(leader_a, leader_b))
+                .then_with(|| leaders[a].cmp(&leaders[b]))
+        });
+        let active_id = self.active_index.map(|i| self.entries[i].id);
+        let mut slots: Vec<Option<InitiativeTrackerEntry>> = std::mem::take(&mut self.entries)
+            .into_iter()
+            .map(Some)
+            .collect();
+        self.entries = order.into_iter().filter_map(|i| slots[i].take()).collect();
+        self.restore_active(active_id);
+    }
+
+    /// Points `active_index` back at the turn of the entry with `active_id`.
+    fn restore_active(&mut self, active_id: Option<u64>) {
+        self.active_index = active_id
+            .and_then(|id| self.entries.iter().position(|e| e.id == id))
+            .map(|i| self.turn_span(i).start);
+    }
+
+    /// For each entry, the index of the first member of its group (its own index if ungrouped).
+    fn group_leaders(&self) -> Vec<usize> {
+        let mut first_members: HashMap<u64, usize> = HashMap::new();
+        self.entries
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| match entry.group_id {
+                Some(group_id) => *first_members.entry(group_id).or_insert(i),
+                None => i,
+            })
+            .collect()
+    }
+
+    /// Gives every group member its group's initiative and tie-break placement.
+    fn sync_groups(&mut self) {
+        for (i, leader) in self.group_leaders().into_iter().enumerate() {
+            if leader == i {
+                continue;
+            }
+            let (initiative, roll_off, rank, delay_rank) = {
+                let leader = &self.entries[leader];
+                (
+                    leader.initiative,
+                    leader.roll_off,
+                    leader.tie_break_rank,
+                    leader.delay_rank,
+                )
+            };
+            let member = &mut self.entries[i];
+            member.initiative = initiative;
+            member.roll_off = roll_off;
+            member.tie_break_rank = rank;
+            member.delay_rank = delay_rank;
+        }
+    }
+
+    /// The entries that take their turn together with the one at `index`.
+    fn turn_span(&self, index: usize) -> Range<usize> {
+        let Some(group_id) = self.entries[index].group_id else {
+            return index..index + 1;
+        };
+        let outside = |e: &InitiativeTrackerEntry| e.group_id != Some(group_id);
+        let start = self.entries[..index]
+            .iter()
+            .rposition(outside)
+            .map_or(0, |i| i + 1);
+        let end = self.entries[index..]
+            .iter()
+            .position(outside)
+            .map_or(self.entries.len(), |i| index + i);
+        start..end
+    }
+
+    /// The index of the first entry of every turn, in order.
+    fn turn_starts(&self) -> Vec<usize> {
+        (0..self.entries.len())
+            .filter(|&i| {
+                i == 0
+                    || self.entries[i].group_id.is_none()
+                    || self.entries[i].group_id != self.entries[i - 1].group_id
+            })
+            .collect()
+    }
+
+    /// The name of the turn that starts at `index`: the group's name for a group.
+    fn turn_name(&self, index: usize) -> String {
+        let entry = &self.entries[index];
+        entry
+            .group_id
+            .and_then(|id| self.groups.iter().find(|g| g.id == id))
+            .map(|group| group.name.clone())
+            .unwrap_or_else(|| entry.name.clone())
+    }
+
+    /// Moves an entry into, out of or into a new group, next to its new group's members.
+    fn change_group(&mut self, index: usize, change: GroupChange) {
+        let active_id = self.active_index.map(|i| self.entries[i].id);
+        let mut entry = self.entries.remove(index);
+        let old_group = entry.group_id;
+        let (new_group, anchor) = match change {
+            GroupChange::Leave => (None, old_group),
+            GroupChange::Join(id) => (Some(id), Some(id)),
+            GroupChange::New => {
+                let group = EntryGroup::new(&entry.name);
+                let id = group.id;
+                self.groups.push(group);
+                (Some(id), old_group)
+            }
+        };
+        entry.group_id = new_group;
+        // Joiners take the group's initiative; leavers keep theirs.
+        if let GroupChange::Join(id) = change
+            && let Some(leader) = self.entries.iter().find(|e| e.group_id == Some(id))
+        {
+            entry.initiative = leader.initiative;
+            entry.roll_off = leader.roll_off;
+            entry.tie_break_rank = leader.tie_break_rank;
+            entry.delay_rank = leader.delay_rank;
+        }
+        // Go right after the group joined or left, or stay in place.
+        let position = anchor
+            .and_then(|id| self.entries.iter().rposition(|e| e.group_id == Some(id)))
+            .map_or(index, |last| last + 1);
+        self.entries.insert(position, entry);
+        self.restore_active(active_id);
+        self.prune_groups();
+    }
+
+    /// Splits a group back into separate entries.
+    fn ungroup(&mut self, group_id: u64) {
+        for entry in self
+            .entries
+            .iter_mut()
+            .filter(|e| e.group_id == Some(group_id))
+        {
+            entry.group_id = None;
+        }
+        self.prune_groups();
+    }
+
+    /// Drops groups without members and group ids that point at no group.
+    fn prune_groups(&mut self) {
+        let entries = &self.entries;
+        self.groups
+            .retain(|group| entries.iter().any(|e| e.group_id == Some(group.id)));
+        let groups = &self.groups;
+        for entry in self.entries.iter_mut() {
+            if entry
+                .group_id
+                .is_some_and(|id| !groups.iter().any(|g| g.id == id))
+            {
+                entry.group_id = None;
+            }
+        }
+    }
+
+    /// Positions of the entries with the given ids, in list order.
+    fn indices_of(&self, ids: &[u64]) -> Vec<usize> {
+        self.entries
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| ids.contains(&entry.id))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Swaps the tie-break ranks of two turns and reorders the list to match.
+    fn swap_tie_break_ranks(&mut self, index_a: usize, index_b: usize) {
+        let rank_a = self.entries[index_a].tie_break_rank;
+        self.entries[index_a].tie_break_rank = self.entries[index_b].tie_break_rank;
+        self.entries[index_b].tie_break_rank = rank_a;
+        self.sync_groups();
+        self.order_entries();
+    }
+
+    /// Rolls d20 + initiative modifier for one entry.
+    fn roll_initiative_for(&mut self, index: usize) {
+        let entry = &mut self.entries[index];
+        let check = DiceExpr::d20_check(entry.initiative_modifier, entry.initiative_roll_mode);
+        entry.initiative = check.roll(&mut self.rng).total;
+        entry.clear_tie_break();
+        // A group rolls once, with its first member's modifier.
+        self.sync_groups();
+    }
+
+    /// Rolls initiative for every entry (or only the NPCs) and then sorts.
+    fn roll_initiative(&mut self, npcs_only: bool) {
+        for i in self.turn_starts() {
+            let entry = &self.entries[i];
+            // The lair always acts on count 20.
+            if !(entry.lair_actions || npcs_only && entry.is_pc) {
+                self.roll_initiative_for(i);
+            }
+        }
+        self.sort_by_initiative();
+    }
+
+    /// Serializes the fight for the undo history.
+    fn snapshot(&self) -> String {
+        let state = EncounterStateRef {
+            entries: &self.entries,
+            active_index: self.active_index,
+            round_count: self.round_count,
+            show_tie_breaker: self.show_tie_breaker,
+            tie_groups: &self.tie_groups,
+            groups: &self.groups,
+            turn_steps: &self.turn_steps,
+        };
+        // Plain data, so serializing can't fail.
+        serde_json::to_string(&state).unwrap_or_default()
+    }
+
+    /// Puts the fight back to a snapshot taken by `snapshot`.
+    fn restore(&mut self, snapshot: &str) {
+        match serde_json::from_str::<EncounterState>(snapshot) {
+            Ok(state) => {
+                self.entries = state.entries;
+                self.active_index = state.active_index;
+                self.round_count = state.round_count;
+                self.show_tie_breaker = state.show_tie_breaker;
+                self.tie_groups = state.tie_groups;
+                self.groups = state.groups;
+                self.turn_steps = state.turn_steps;
+            }
+            Err(e) => eprintln!("Failed to restore undo step: {}", e),
+        }
+    }
+
+    fn undo(&mut self) {
+        if let Some(snapshot) = self.history.undo() {
+            self.restore(&snapshot);
+        }
+    }
+
+    fn redo(&mut self) {
+        if let Some(snapshot) = self.history.redo() {
+            self.restore(&snapshot);
+        }
+    }
+
+    /// Records an undo step for whatever changed this frame. Waits while a value
+    /// is being dragged or typed, so one edit becomes one step.
+    fn record_history(&mut self, ctx: &egui::Context) {
+        // Only clicks, key presses and typing change the fight; pointer moves and repaints don't.
+        let changing_input = ctx.input(|i| {
+            i.events.iter().any(|event| {
+                matches!(
+                    event,
+                    egui::Event::Key { .. }
+                        | egui::Event::Text(_)
+                        | egui::Event::Paste(_)
+                        | egui::Event::PointerButton { .. }
+                )
+            })
+        });
+        self.history_up_to_date &= !changing_input;
+        let editing = ctx.is_using_pointer() || ctx.memory(|m| m.focused().is_some());
+        if !editing && !self.history_up_to_date {
+            self.history.record(self.snapshot());
+            self.history_up_to_date = true;
+        }
+    }
+
+    /// Ctrl+Z undoes, Ctrl+Shift+Z or Ctrl+Y redoes. Left alone while a text field has focus,
+    /// so it can undo its own typing, and while a hotkey is being rebound.
+    fn handle_undo_shortcuts(&mut self, ctx: &egui::Context) {
+        if ctx.memory(|m| m.focused().is_some()) || self.settings.keymap.is_rebinding() {
+            return;
+        }
+        let redo_shortcut = egui::KeyboardShortcut::new(
+            egui::Modifiers::COMMAND | egui::Modifiers::SHIFT,
+            egui::Key::Z,
+        );
+        let other_redo_shortcut =
+            egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Y);
+        let undo_shortcut = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Z);
+        // Check redo first: consuming Ctrl+Z would also match Ctrl+Shift+Z.
+        if ctx.input_mut(|i| {
+            i.consume_shortcut(&redo_shortcut) || i.consume_shortcut(&other_redo_shortcut)
+        }) {
+            self.redo();
+        } else if ctx.input_mut(|i| i.consume_shortcut(&undo_shortcut)) {
+            self.undo();
+        }
+    }
+
+    /// Runs the action for any hotkey pressed this frame. Left alone while a text
+    /// field has focus or a hotkey is being rebound.
+    fn handle_hotkeys(&mut self, ctx: &egui::Context) {
+        if ctx.memory(|m| m.focused().is_some()) || self.settings.keymap.is_rebinding() {
+            return;
+        }
+        let Some(action) = self.settings.keymap.pressed(ctx) else {
+            return;
+        };
+        match action {
+            HotkeyAction::NextTurn => self.next_turn(),
+            HotkeyAction::PreviousTurn => self.previous_turn(),
+            HotkeyAction::FocusDamage => {
+                // The lair has no HP to change.
+                if let Some(active) = self.active_index
+                    && !self.entries[active].lair_actions
+                {
+                    self.jump_to(self.entries[active].id);
+                }
+            }
+            HotkeyAction::AddTrack => {
+                let entry = InitiativeTrackerEntry::default();
+                self.card_focus = Some((entry.id, CardField::Name));
+                self.add_entry(entry);
+            }
+            HotkeyAction::Sort => self.sort_by_initiative(),
+            HotkeyAction::FindCombatant => self.find_query = Some(String::new()),
+            HotkeyAction::ShowCheatsheet => self.show_cheatsheet = !self.show_cheatsheet,
+        }
+    }
+
+    /// Scrolls to the card of the entry with `id` and puts the cursor in its
+    /// damage field, or the lair's name field, opening its group if it is folded away.
+    fn jump_to(&mut self, id: u64) {
+        let Some(entry) = self.entries.iter().find(|e| e.id == id) else {
+            return;
+        };
+        if let Some(group) = self
+            .groups
+            .iter_mut()
+            .find(|g| Some(g.id) == entry.group_id)
+        {
+            group.collapsed = false;
+        }
+        // The lair's card has no damage field to take the focus.
+        let field = if entry.lair_actions {
+            CardField::Name
+        } else {
+            CardField::Damage
+        };
+        self.card_focus = Some((id, field));
+    }
+
+    /// The "Jump to Combatant" box: type part of a name, Enter picks the first match.
+    fn find_window(&mut self, ctx: &egui::Context) {
+        let Some(query) = &mut self.find_query else {
+            return;
+        };
+        let mut picked: Option<u64> = None;
+        let mut close = false;
+        egui::Window::new("Jump to Combatant")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_TOP, [0.0, 60.0])
+            .show(ctx, |ui| {
+                let response = ui.add(
+                    egui::TextEdit::singleline(query)
+                        .hint_text("Name")
+                        .desired_width(200.0),
+                );
+                let needle = query.to_lowercase();
+                let matches: Vec<&InitiativeTrackerEntry> = self
+                    .entries
+                    .iter()
+                    .filter(|e| e.name.to_lowercase().contains(&needle))
+                    .collect();
+                for entry in &matches {
+                    if ui.selectable_label(false, &entry.name).clicked() {
+                        picked = Some(entry.id);
+                    }
+                }
+                if matches.is_empty() {
+                    ui.weak("No match");
+                }
+                if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                    close = true;
+                } else if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                    picked = matches.first().map(|e| e.id);
+                    close = true;
+                } else if picked.is_none() {
+                    response.request_focus();
+                }
+            });
+        if let Some(id) = picked {
+            self.jump_to(id);
+            close = true;
+        }
+        if close {
+            self.find_query = None;
+        }
+    }
+
+    /// Moves to the next combatant, starting a new round after the last one,
+    /// and counts down the conditions tied to the turns that end and start.
+    fn next_turn(&mut self) {
+        // If there are no entries, do nothing
+        if self.entries.is_empty() {
+            return;
+        }
+        self.begin_turn_step();
+        let surprised: Vec<u64> = self
+            .entries
+            .iter()
+            .filter(|e| e.surprised)
+            .map(|e| e.id)
+            .collect();
+        // Move to the next combatant
+        let mut next_index = match self.active_index {
+            Some(active_index) => {
+                let span = self.turn_span(active_index);
+                self.end_turn(active_index);
+                self.turn_after(span.end)
+            }
+            // If no active index, set it to the first entry
+            None => 0,
+        };
+        // Surprised creatures lose their turn in the first round.
+        while self.round_count == 1
+            && self
+                .turn_span(next_index)
+                .all(|i| self.entries[i].surprised)
+        {
+            let span = self.turn_span(next_index);
+            for i in span.clone() {
+                self.entries[i].surprised = false;
+            }
+            self.log(CombatEvent::Surprised {
+                name: self.turn_name(next_index),
+            });
+            next_index = self.turn_after(span.end);
+        }
+        let surprise_ended = surprised
+            .into_iter()
+            .filter(|&id| self.entries.iter().any(|e| e.id == id && !e.surprised))
+            .collect();
+        self.turn_steps.record_surprise_ended(surprise_ended);
+        self.start_turn(next_index);
+    }
+
+    /// Starts recording a turn change for "Previous Turn".
+    fn begin_turn_step(&mut self) {
+        let active_id = self.active_index.map(|i| self.entries[i].id);
+        self.turn_steps
+            .begin(active_id, self.round_count, self.death_save_prompt);
+    }
+
+    /// Steps back over the last turn change, e.g. after clicking "Next Turn" once
+    /// too often: the combatant before is active again in their round, and what
+    /// the change did (condition ticks, lost surprise, a delay, regained legendary
+    /// actions, lost readied actions and a due death save) is given back.
+    /// Without a recorded change (e.g. older than the ones kept) it moves to the turn
+    /// before in the order, going back a round from the first one but not below round 1.
+    fn previous_turn(&mut self) {
+        let Some(active_index) = self.active_index else {
+            return;
+        };
+        if let Some(step) = self.turn_steps.pop() {
+            for tick in step.ticks.into_iter().rev() {
+                self.untick_condition(tick);
+            }
+            for reset in step.turn_starts.into_iter().rev() {
+                if let Some(entry) = self.entries.iter_mut().find(|e| e.id == reset.entry_id) {
+                    entry.legendary_actions_left = reset.legendary_actions_left;
+                    entry.readied_action = reset.readied_action;
+                }
+            }
+            if let Some(delay) = step.delay {
+                self.undo_delay(delay);
+            }
+            for entry in self.entries.iter_mut() {
+                if step.surprise_ended.contains(&entry.id) {
+                    entry.surprised = true;
+                }
+                if step.delaying_ended.contains(&entry.id) {
+                    entry.delaying = true;
+                }
+            }
+            self.death_save_prompt = step.death_save_prompt;
+            self.round_count = step.round;
+            match step.active_id {
+                // Before the first turn: combat hasn't started yet.
+                None => {
+                    self.active_index = None;
+                    return;
+                }
+                Some(id) if self.entries.iter().any(|e| e.id == id) => {
+                    self.restore_active(Some(id));
+                }
+                // They have left the fight: fall back to the order.
+                Some(_) => self.active_index = Some(self.turn_before(active_index)),
+            }
+        } else {
+            let starts = self.turn_starts();
+            if starts.first() == Some(&active_index) {
+                if self.round_count == 1 {
+                    return;
+                }
+                self.round_count -= 1;
+            }
+            self.active_index = Some(self.turn_before(active_index));
+        }
+        if let Some(index) = self.active_index {
+            self.log(CombatEvent::TurnReversed {
+                name: self.turn_name(index),
+            });
+        }
+    }
+
+    /// Puts a delayer back where they were before delaying, with their old initiative.
+    fn undo_delay(&mut self, delay: DelayMove) {
+        let Some(from) = self.entries.iter().position(|e| e.id == delay.entry_id) else {
+            return;
+        };
+        let mut entry = self.entries.remove(from);
+        entry.initiative = delay.initiative;
+        entry.tie_break_rank = delay.tie_break_rank;
+        entry.roll_off = delay.roll_off;
+        entry.delaying = false;
+        let index = delay.index.min(self.entries.len());
+        self.entries.insert(index, entry);
+        for entry in self.entries.iter_mut() {
+            if let Some((_, rank)) = delay.delay_ranks.iter().find(|(id, _)| *id == entry.id) {
+                entry.delay_rank = *rank;
+            }
+        }
+    }
+
+    /// The start of the turn before the one starting at `index`, wrapping to the last.
+    fn turn_before(&self, index: usize) -> usize {
+        let starts = self.turn_starts();
+        match starts.iter().rposition(|&start| start < index) {
+            Some(position) => starts[position],
+            None => starts.last().copied().unwrap_or(0),
+        }
+    }
+
+    /// Makes it the turn of the entry at `index` (and their group), e.g. when the
+    /// table lost track: the current turn ends and theirs starts, in the same round.
+    /// "Previous Turn" steps back to the combatant who was active before.
+    fn make_active(&mut self, index: usize) {
+        let start = self.turn_span(index).start;
+        if self.active_index == Some(start) {
+            return;
+        }
+        self.begin_turn_step();
+        if let Some(active_index) = self.active_index {
+            self.end_turn(active_index);
+        }
+        self.start_turn(start);
+    }
+
+    /// Does the end-of-turn bookkeeping for everyone taking the turn at `index`.
+    /// A delayer finishing their delayed turn skips it: their turn ended when they delayed.
+    fn end_turn(&mut self, index: usize) {
+        for i in self.turn_span(index) {
+            if self.entries[i].delaying {
+                self.entries[i].delaying = false;
+                self.turn_steps.record_delaying_ended([self.entries[i].id]);
+                continue;
+            }
+            let ending_id = self.entries[i].id;
+            self.tick_conditions(ending_id, TurnBoundary::End);
+        }
+    }
+
+    /// The turn following one that ends before `end`, starting a new round after the last.
+    fn turn_after(&mut self, end: usize) -> usize {
+        let next_index = end % self.entries.len();
+        // if next index is 0, increment the round count
+        if next_index == 0 {
+            self.round_count += 1;
+            self.log(CombatEvent::RoundStarted {
+                round: self.round_count,
+            });
+            // Surprise only lasts the first round, and a delay only this one.
+            let delaying = self.entries.iter().filter(|e| e.delaying).map(|e| e.id);
+            self.turn_steps.record_delaying_ended(delaying);
+            for entry in self.entries.iter_mut() {
+                entry.surprised = false;
+                entry.delaying = false;
+            }
+        }
+        next_index
+    }
+
+    /// Shows a hidden entry to the players. An ungrouped entry also moves to its
+    /// place in the order, e.g. an ambusher added at the bottom mid-fight,
+    /// while the current turn stays where it is.
+    fn reveal(&mut self, index: usize) {
+        self.entries[index].hidden = false;
+        self.log(CombatEvent::Revealed {
+            name: self.entries[index].name.clone(),
+        });
+        if self.entries[index].group_id.is_some() {
+            return;
+        }
+        let rules = &self.settings.tie_break_rules;
+        // The rules first, then the order settled in the tie-breaker.
+        let acts_before = |a: &InitiativeTrackerEntry, b: &InitiativeTrackerEntry| {
+            compare_turn_order(a, b, rules).then_with(|| compare_tie_break_rank(a, b))
+                == Ordering::Less
+        };
+        // Stay put when already in place, e.g. a tied entry that has acted.
+        let starts = self.turn_starts();
+        let entry = &self.entries[index];
+        let in_place = starts
+            .iter()
+            .rev()
+            .find(|&&start| start < index)
+            .is_none_or(|&start| !acts_before(entry, &self.entries[start]))
+            && starts
+                .iter()
+                .find(|&&start| start > index)
+                .is_none_or(|&start| !acts_before(&self.entries[start], entry));
+        if in_place {
+            return;
+        }
+        let active_id = self.active_index.map(|i| self.entries[i].id);
+        let entry = self.entries.remove(index);
+        // Before the first turn it would act ahead of.
+        let position = self
+            .turn_starts()
+            .into_iter()
+            .find(|&start| acts_before(&entry, &self.entries[start]))
+            .unwrap_or(self.entries.len());
+        self.entries.insert(position, entry);
+        self.restore_active(active_id);
+    }
+
+    /// Makes the turn starting at `index` active and does the start-of-turn
+    /// bookkeeping for everyone taking it.
+    /// A delayer coming back to act skips it: their turn already started.
+    fn start_turn(&mut self, index: usize) {
+        self.active_index = Some(index);
+        if !self.turn_span(index).all(|i| self.entries[i].delaying) {
+            self.log(CombatEvent::TurnStarted {
+                name: self.turn_name(index),
+            });
+        }
+        for i in self.turn_span(index) {
+            if self.entries[i].delaying {
+                continue;
+            }
+            let starting_id = self.entries[i].id;
+            self.tick_conditions(starting_id, TurnBoundary::Start);
+            // Legendary actions are regained at the start of the creature's turn,
+            // and an unused readied action is lost.
+            let starting = &mut self.entries[i];
+            self.turn_steps.record_turn_start(TurnStartReset {
+                entry_id: starting_id,
+                legendary_actions_left: starting.legendary_actions_left,
+                readied_action: starting.readied_action.clone(),
+            });
+            starting.legendary_actions_left = starting.legendary_actions;
+            starting.readied_action = None;
+            // A dying PC starts their turn with a death save.
+            if starting.is_dying() {
+                self.death_save_prompt = Some(starting_id);
+            }
+        }
+    }
+
+    /// The active combatant delays: their turn ends for now and they move to just
+    /// after `after_id`, who must act later this round, and the next combatant's
+    /// turn starts. When the delayer comes up again they act without a new start of turn.
+    fn delay_until_after(&mut self, after_id: u64) {
+        let Some(active_index) = self.active_index else {
+            return;
+        };
+        let Some(after_index) = self.entries.iter().position(|e| e.id == after_id) else {
+            return;
+        };
+        if after_index <= active_index {
+            return;
+        }
+        self.begin_turn_step();
+        let delayer = &self.entries[active_index];
+        self.turn_steps.record_delay(DelayMove {
+            entry_id: delayer.id,
+            index: active_index,
+            initiative: delayer.initiative,
+            tie_break_rank: delayer.tie_break_rank,
+            roll_off: delayer.roll_off,
+            delay_ranks: self.entries.iter().map(|e| (e.id, e.delay_rank)).collect(),
+        });
+        // Their turn ends now, unless it already did when they delayed before.
+        self.end_turn(active_index);
+        let mut entry = self.entries.remove(active_index);
+        entry.delaying = true;
+        // Share the initiative of the creature they follow, placed right after it.
+        let after = &self.entries[after_index - 1];
+        entry.initiative = after.initiative;
+        entry.clear_tie_break();
+        let after_name = self.turn_name(after_index - 1);
+        self.log(CombatEvent::Delayed {
+            name: entry.name.clone(),
+            after: after_name,
+        });
+        self.entries.insert(after_index, entry);
+        self.settle_ties_around(after_index);
+        // Everyone behind the delayer moved up one, so the next creature is at the same index.
+        self.start_turn(active_index);
+    }
+
+    /// Moves the entry with `moved_id` just before or after the entry with
+    /// `target_id`, e.g. when its card is dragged in the list. A group moves as
+    /// a whole unless the entry is dropped among its own group's members.
+    /// The active turn stays with the same creature; sorting undoes the move.
+    fn move_entry(&mut self, moved_id: u64, target_id: u64, after: bool) {
+        let Some(from) = self.entries.iter().position(|e| e.id == moved_id) else {
+            return;
+        };
+        let Some(target) = self.entries.iter().position(|e| e.id == target_id) else {
+            return;
+        };
+        let (moved, target) = if self.turn_span(from).contains(&target) {
+            (from..from + 1, target..target + 1)
+        } else {
+            (self.turn_span(from), self.turn_span(target))
+        };
+        if moved.contains(&target.start) {
+            return;
+        }
+        let active_id = self.active_index.map(|i| self.entries[i].id);
+        let mut position = if after { target.end } else { target.start };
+        if position > moved.start {
+            position -= moved.len();
+        }
+        let moving: Vec<InitiativeTrackerEntry> = self.entries.drain(moved).collect();
+        self.entries.splice(position..position, moving);
+        self.restore_active(active_id);
+    }
+
+    /// Ranks the entries tied on initiative with the one at `index` in their
+    /// current order, so re-sorting keeps them that way whatever the tie-break rules say.
+    fn settle_ties_around(&mut self, index: usize) {
+        let initiative = self.entries[index].initiative;
+        for (rank, entry) in self
+            .entries
+            .iter_mut()
+            .filter(|e| e.initiative == initiative)
+            .enumerate()
+        {
+            entry.delay_rank = Some(rank as u32);
+        }
+    }
+
+    /// Records a readied action for an entry from its card's form.
+    fn ready_action(&mut self, index: usize) {
+        let entry = &mut self.entries[index];
+        let trigger = entry.ready_form.trim().to_string();
+        if trigger.is_empty() {
+            return;
+        }
+        entry.ready_form.clear();
+        entry.readied_action = Some(trigger.clone());
+        let event = CombatEvent::ActionReadied {
+            name: entry.name.clone(),
+            trigger,
+        };
+        self.log(event);
+    }
+
+    /// The readied action's trigger happened: the entry uses its reaction.
+    fn trigger_readied_action(&mut self, index: usize) {
+        let entry = &mut self.entries[index];
+        let Some(trigger) = entry.readied_action.take() else {
+            return;
+        };
+        let event = CombatEvent::ReadiedActionTriggered {
+            name: entry.name.clone(),
+            trigger,
+        };
+        self.log(event);
+    }
+
+    /// Records a death save for an entry; `None` rolls the d20.
+    fn death_save(&mut self, index: usize, result: Option<DeathSaveResult>) {
+        let (result, rolled) = match result {
+            Some(result) => (result, None),
+            None => {
+                let roll = self.rng.i32(1..=20);
+                (DeathSaveResult::from_roll(roll), Some(roll))
+            }
+        };
+        let entry = &mut self.entries[index];
+        entry.death_saves.record(result);
+        if result == DeathSaveResult::CriticalSuccess {
+            entry.hp_current = 1;
+        }
+        let status = if result == DeathSaveResult::CriticalSuccess {
+            "regains 1 HP".to_string()
+        } else {
+            entry.death_saves.status()
+        };
+        let outcome = match rolled {
+            Some(roll) => format!("rolled {}: {}", roll, status),
+            None => status,
+        };
+        let target = entry.name.clone();
+        let died = entry.death_saves.dead;
+        self.log(CombatEvent::DeathSave {
+            target: target.clone(),
+            outcome,
+        });
+        if died {
+            self.log(CombatEvent::Died { target });
+        }
+    }
+
+    /// The pop-up asking for the death save of the PC whose turn it is.
+    fn death_save_window(&mut self, ctx: &egui::Context) {
+        let Some(id) = self.death_save_prompt else {
+            return;
+        };
+        // Close the prompt once the PC is up, stable or dead, or gone from the fight.
+        let Some(index) = self.entries.iter().position(|e| e.id == id && e.is_dying()) else {
+            self.death_save_prompt = None;
+            return;
+        };
+        let mut result: Option<Option<DeathSaveResult>> = None;
+        let mut open = true;
+        egui::Window::new("Death Saving Throw")
+            .collapsible(false)
+            .resizable(false)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                let entry = &self.entries[index];
+                ui.label(format!("{} is dying.", entry.name));
+                ui.label(format!(
+                    "Successes {}  Failures {}",
+                    DeathSaves::pips(entry.death_saves.successes),
+                    DeathSaves::pips(entry.death_saves.failures)
+                ));
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.button("Roll d20").clicked() {
+                        result = Some(None);
+                    }
+                    ui.label("or enter the player's roll:");
+                });
+                ui.horizontal(|ui| {
+                    if ui.button("Success").clicked() {
+                        result = Some(Some(DeathSaveResult::Success));
+                    }
+                    if ui.button("Failure").clicked() {
+                        result = Some(Some(DeathSaveResult::Failure));
+                    }
+                    if ui.button("Natural 20").clicked() {
+                        result = Some(Some(DeathSaveResult::CriticalSuccess));
+                    }
+                    if ui.button("Natural 1").clicked() {
+                        result = Some(Some(DeathSaveResult::CriticalFailure));
+                    }
+                });
+            });
+        if let Some(result) = result {
+            self.death_save(index, result);
+            self.death_save_prompt = None;
+        }
+        if !open {
+            self.death_save_prompt = None;
+        }
+    }
+
+    /// Counts down every timed condition whose duration runs on `turn_id`'s turns
+    /// at this boundary, and removes the ones that run out. The ticks are recorded
+    /// on the current turn step so "Previous Turn" can give them back.
+    fn tick_conditions(&mut self, turn_id: u64, boundary: TurnBoundary) {
+        // Conditions whose source has left the fight fall back to the affected creature's turns.
+        let ids: Vec<u64> = self.entries.iter().map(|e| e.id).collect();
+        let mut ticks: Vec<ConditionTick> = Vec::new();
+        for entry in self.entries.iter_mut() {
+            let entry_id = entry.id;
+            let mut expired: Vec<String> = Vec::new();
+            let mut position = 0;
+            entry.conditions.retain_mut(|condition| {
+                // Its place in the list, for "Previous Turn" to put it back if it expires.
+                let index = position;
+                position += 1;
+                let counts_on = condition
+                    .source_id
+                    .filter(|id| ids.contains(id))
+                    .unwrap_or(entry_id);
+                if counts_on != turn_id || condition.ends_at != boundary {
+                    return true;
+                }
+                let Some(rounds) = condition.rounds_remaining.as_mut() else {
+                    return true;
+                };
+                let mut tick = |effect| {
+                    ticks.push(ConditionTick {
+                        entry_id,
+                        condition_id: condition.id,
+                        effect,
+                    })
+                };
+                if condition.skip_next_tick {
+                    condition.skip_next_tick = false;
+                    tick(TickEffect::Skipped);
+                    return true;
+                }
+                *rounds = rounds.saturating_sub(1);
+                if *rounds == 0 {
+                    expired.push(condition.kind.name().to_string());
+                    tick(TickEffect::Expired {
+                        condition: condition.clone(),
+                        index,
+                    });
+                    return false;
+                }
+                tick(TickEffect::CountedDown);
+                true
+            });
+            for name in expired {
+                self.notices.push(format!(
+                    "Round {}: {} is no longer {}.",
+                    self.round_count, entry.name, name
+                ));
+                self.combat_log.push(
+                    self.round_count,
+                    CombatEvent::ConditionExpired {
+                        target: entry.name.clone(),
+                        condition: name,
+                    },
+                );
+            }
+        }
+        self.turn_steps.record_ticks(ticks);
+    }
+
+    /// Gives back what a recorded tick did to a condition.
+    fn untick_condition(&mut self, tick: ConditionTick) {
+        let Some(entry) = self.entries.iter_mut().find(|e| e.id == tick.entry_id) else {
+            return;
+        };
+        if let TickEffect::Expired {
+            mut condition,
+            index,
+        } = tick.effect
+        {
+            // It ran out on this tick, so it had one round left.
+            condition.rounds_remaining = Some(1);
+            let index = index.min(entry.conditions.len());
+            entry.conditions.insert(index, condition);
+            return;
+        }
+        // Conditions removed by hand since stay removed.
+        let Some(condition) = entry
+            .conditions
+            .iter_mut()
+            .find(|c| c.id == tick.condition_id)
+        else {
+            return;
+        };
+        match tick.effect {
+            TickEffect::Skipped => condition.skip_next_tick = true,
+            TickEffect::CountedDown => {
+                if let Some(rounds) = condition.rounds_remaining.as_mut() {
+                    *rounds += 1;
+                }
+            }
+            TickEffect::Expired { .. } => {}
+        }
+    }
+
+    /// Adds the condition described by an entry's form to that entry.
+    fn add_condition(&mut self, index: usize) {
+        let concentrating: Vec<u64> = self
+            .entries
+            .iter()
+            .filter(|e| e.concentrating)
+            .map(|e| e.id)
+            .collect();
+        let entry = &mut self.entries[index];
+        let Some(mut condition) = entry.condition_form.build(&concentrating) else {
+            return;
+        };
+        // Don't keep offering a link to concentration that has since ended.
+        entry.condition_form.concentration_id = condition.concentration_id;
+        // Added during the source's own turn (or their group's): that turn ending
+        // shouldn't use up a round.
+        let counts_on = condition.source_id.unwrap_or(entry.id);
+        let during_their_turn = self.active_index.is_some_and(|active| {
+            self.turn_span(active)
+                .any(|i| self.entries[i].id == counts_on)
+        });
+        if condition.ends_at == TurnBoundary::End && during_their_turn {
+            condition.skip_next_tick = true;
+        }
+        self.log(CombatEvent::ConditionApplied {
+            target: self.entries[index].name.clone(),
+            condition: condition.kind.name().to_string(),
+        });
+        self.entries[index].conditions.push(condition);
+    }
+
+    /// Removes one condition from an entry by hand.
+    fn remove_condition(&mut self, index: usize, condition_index: usize) {
+        let condition = self.entries[index].conditions.remove(condition_index);
+        self.log(CombatEvent::ConditionRemoved {
+            target: self.entries[index].name.clone(),
+            condition: condition.kind.name().to_string(),
+        });
+    }
+
+    /// A short description of a condition for its track card,
+    /// e.g. "Stunned (1 rd, ends at end of Wizard's turn)".
+    fn describe_condition(&self, index: usize, condition: &Condition) -> String {
+        let concentrator = condition
+            .concentration_id
+            .and_then(|id| self.entries.iter().find(|e| e.id == id))
+            .map(|caster| format!("{}'s concentration", caster.name));
+        let Some(rounds) = condition.rounds_remaining else {
+            return match concentrator {
+                Some(concentrator) => format!("{} ({})", condition.kind.name(), concentrator),
+                None => condition.kind.name().to_string(),
+            };
+        };
+        let concentration = concentrator
+            .map(|concentrator| format!(", {}", concentrator))
+            .unwrap_or_default();
+        let whose = match condition
+            .source_id
+            .filter(|&id| id != self.entries[index].id)
+            .and_then(|id| self.entries.iter().find(|e| e.id == id))
+        {
+            Some(source) => format!("{}'s", source.name),
+            None => "own".to_string(),
+        };
+        format!(
+            "{} ({} {}, ends at {} of {} turn{})",
+            condition.kind.name(),
+            rounds,
+            if rounds == 1 { "rd" } else { "rds" },
+            condition.ends_at.label(),
+            whose,
+            concentration
+        )
+    }
+
+    /// Applies the amount in an entry's "Damage/Heal" row.
+    fn apply_hp_update(&mut self, index: usize) {
+        let entry = &mut self.entries[index];
+        let amount = entry.hp_update.max(0);
+        let target = entry.name.clone();
+        let mut died = false;
+        let mut concentration_damage = None;
+        let event = match entry.hp_update_kind {
+            HpUpdateKind::Damage => {
+                let damage_type = entry.hp_update_damage_type;
+                let adjusted = entry.defenses.adjust(amount, damage_type);
+                let was_dead = entry.death_saves.dead;
+                entry.take_damage(adjusted.amount, entry.hp_update_critical);
+                if entry.death_saves.dead && !was_dead {
+                    died = true;
+                }
+                if entry.concentrating && adjusted.amount > 0 {
+                    concentration_damage = Some(adjusted.amount);
+                }
+                let type_name = damage_type.map(|kind| kind.name().to_string());
+                let mut result = format!("Took {}", adjusted.amount);
+                if let Some(name) = &type_name {
+                    result.push_str(&format!(" {}", name));
+                }
+                if let Some(note) = adjusted.note {
+                    result.push_str(&format!(" ({}, {} rolled)", note, amount));
+                }
+                entry.last_hp_update = Some(result);
+                CombatEvent::Damage {
+                    target,
+                    amount: adjusted.amount,
+                    damage_type: type_name,
+                    note: adjusted.note.map(str::to_string),
+                    hp_after: entry.hp_current,
+                    hp_max: entry.hp_max(),
+                }
+            }
+            HpUpdateKind::Heal => {
+                entry.heal(amount);
+                entry.last_hp_update = None;
+                CombatEvent::Healing {
+                    target,
+                    amount,
+                    hp_after: entry.hp_current,
+                    hp_max: entry.hp_max(),
+                }
+            }
+            HpUpdateKind::TempHp => {
+                entry.grant_temp_hp(amount);
+                entry.last_hp_update = None;
+                CombatEvent::TempHp { target, amount }
+            }
+        };
+        self.log(event);
+        if died {
+            self.log(CombatEvent::Died {
+                target: self.entries[index].name.clone(),
+            });
+        }
+        if let Some(damage) = concentration_damage {
+            let entry = &self.entries[index];
+            if entry.hp_current == 0 {
+                // Falling unconscious ends concentration without a save.
+                self.drop_concentration(index);
+            } else {
+                self.concentration_checks.push(ConcentrationCheck {
+                    id: entry.id,
+                    dc: concentration::save_dc(damage),
+                    damage,
+                });
+            }
+        }
+    }
+
+    /// Ends an entry's concentration, along with every condition it was maintaining.
+    fn drop_concentration(&mut self, index: usize) {
+        let entry = &mut self.entries[index];
+        if !entry.concentrating {
+            return;
+        }
+        entry.concentrating = false;
+        let concentrator_id = entry.id;
+        let event = CombatEvent::ConcentrationLost {
+            target: entry.name.clone(),
+            spell: entry.concentration_spell.trim().to_string(),
+        };
+        self.log(event);
+        self.concentration_checks
+            .retain(|c| c.id != concentrator_id);
+        for i in 0..self.entries.len() {
+            while let Some(ci) = self.entries[i]
+                .conditions
+                .iter()
+                .position(|c| c.concentration_id == Some(concentrator_id))
+            {
+                self.remove_condition(i, ci);
+            }
+        }
+    }
+
+    /// Resolves the oldest concentration save: `None` rolls it, otherwise
+    /// whether the player's own roll succeeded.
+    fn concentration_save(&mut self, passed: Option<bool>) {
+        if self.concentration_checks.is_empty() {
+            return;
+        }
+        let check = self.concentration_checks.remove(0);
+        let Some(index) = self.entries.iter().position(|e| e.id == check.id) else {
+            return;
+        };
+        let (passed, outcome) = match passed {
+            Some(passed) => (passed, if passed { "kept" } else { "failed" }.to_string()),
+            None => {
+                let roll = self.rng.i32(1..=20);
+                let total = roll + self.entries[index].con_save_modifier;
+                let passed = total >= check.dc;
+                let result = if passed { "kept" } else { "failed" };
+                (passed, format!("rolled {} ({}): {}", total, roll, result))
+            }
+        };
+        self.log(CombatEvent::ConcentrationSave {
+            target: self.entries[index].name.clone(),
+            dc: check.dc,
+            outcome,
+        });
+        if !passed {
+            self.drop_concentration(index);
+        }
+    }
+
+    /// The pop-up for the oldest concentration save owed.
+    fn concentration_window(&mut self, ctx: &egui::Context) {
+        // Drop checks for entries that left the fight or stopped concentrating.
+        let entries = &self.entries;
+        self.concentration_checks
+            .retain(|check| entries.iter().any(|e| e.id == check.id && e.concentrating));
+        let Some(check) = self.concentration_checks.first() else {
+            return;
+        };
+        let Some(entry) = self.entries.iter_mut().find(|e| e.id == check.id) else {
+            return;
+        };
+        let mut passed: Option<Option<bool>> = None;
+        egui::Window::new("Concentration Save")
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let spell = entry.concentration_spell.trim();
+                ui.label(format!(
+                    "{} took {} damage while concentrating{}.",
+                    entry.name,
+                    check.damage,
+                    if spell.is_empty() {
+                        String::new()
+                    } else {
+                        format!(" on {}", spell)
+                    }
+                ));
+                ui.heading(format!("Constitution save DC {}", check.dc));
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label("Save bonus:");
+                    ui.add(
+                        egui::DragValue::new(&mut entry.con_save_modifier)
+                            .custom_formatter(|n, _| format!("{:+}", n)),
+                    );
+                    if ui.button("Roll").clicked() {
+                        passed = Some(None);
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("or enter the player's result:");
+                    if ui.button("Passed").clicked() {
+                        passed = Some(Some(true));
+                    }
+                    if ui.button("Failed").clicked() {
+                        passed = Some(Some(false));
+                    }
+                });
+                if self.concentration_checks.len() > 1 {
+                    ui.weak(format!(
+                        "{} more saves waiting",
+                        self.concentration_checks.len() - 1
+                    ));
+                }
+            });
+        if let Some(passed) = passed {
+            self.concentration_save(passed);
+        }
+    }
+
+    /// Spends one of an entry's legendary actions.
+    fn use_legendary_action(&mut self, index: usize) {
+        let entry = &mut self.entries[index];
+        if entry.legendary_actions_left == 0 {
+            return;
+        }
+        entry.legendary_actions_left -= 1;
+        let event = CombatEvent::LegendaryAction {
+            name: entry.name.clone(),
+            remaining: entry.legendary_actions_left,
+        };
+        self.log(event);
+    }
+
+    /// Buttons for spending legendary actions at the end of another creature's turn.
+    fn legendary_actions_bar(&mut self, ui: &mut egui::Ui) {
+        let Some(active_index) = self.active_index else {
+            return;
+        };
+        let active_span = self.turn_span(active_index);
+        let ready: Vec<usize> = (0..self.entries.len())
+            .filter(|&i| !active_span.contains(&i) && self.entries[i].legendary_actions_left > 0)
+            .collect();
+        if ready.is_empty() {
+            return;
+        }
+        let mut index_to_use = None;
+        ui.horizontal_wrapped(|ui| {
+            ui.label("Legendary actions:");
+            for i in ready {
+                let entry = &self.entries[i];
+                let text = format!(
+                    "{} ({}/{})",
+                    entry.name, entry.legendary_actions_left, entry.legendary_actions
+                );
+                if ui
+                    .button(text)
+                    .on_hover_text("Use a legendary action")
+                    .clicked()
+                {
+                    index_to_use = Some(i);
+                }
+            }
+        });
+        if let Some(index) = index_to_use {
+            self.use_legendary_action(index);
+        }
+        ui.separator();
+    }
+
+    /// Records an event in the combat log for the current round.
+    fn log(&mut self, event: CombatEvent) {
+        self.combat_log.push(self.round_count, event);
+    }
+
+    /// Adds a combatant to the end of the list.
+    fn add_entry(&mut self, entry: InitiativeTrackerEntry) {
+        self.log(CombatEvent::CombatantAdded {
+            name: entry.name.clone(),
+        });
+        self.entries.push(entry);
+    }
+
+    /// Adds the copies described by the "Add Multiple" form, each with its own HP roll.
+    /// Returns false, with the error on the form, if the HP can't be parsed.
+    fn add_multiple(&mut self) -> bool {
+        let form = &self.bulk_add;
+        let hp_dice = match form.check_name().and_then(|()| form.hp_dice()) {
+            Ok(dice) => dice,
+            Err(e) => {
+                self.bulk_add.error = Some(e);
+                return false;
+            }
+        };
+        let existing: Vec<&str> = self.entries.iter().map(|e| e.name.as_str()).collect();
+        let names = form.names(&existing);
+        let group = form.as_group.then(|| EntryGroup::new(form.name.trim()));
+        let template = InitiativeTrackerEntry {
+            armor_class: form.armor_class,
+            initiative_modifier: form.initiative_modifier,
+            group_id: group.as_ref().map(|g| g.id),
+            ..InitiativeTrackerEntry::default()
+        };
+        if let Some(group) = group {
+            self.groups.push(group);
+        }
+        for name in names {
+            let hp = hp_dice.roll(&mut self.rng).total.max(1);
+            self.add_entry(InitiativeTrackerEntry {
+                id: fastrand::u64(..),
+                name,
+                hp_current: hp,
+                hp_total: hp,
+                ..template.clone()
+            });
+        }
+        self.bulk_add.error = None;
+        true
+    }
+
+    /// Adds combatants sent over from another sub-app, numbering repeated names.
+    pub fn add_combatants(&mut self, combatants: &[InitiativeTrackerEntry]) {
+        // Sent from another sub-app, so no input in the tracker marks the change.
+        self.history_up_to_date = false;
+        for combatant in combatants {
+            let mut name = combatant.name.clone();
+            let mut n = 1;
+            while self.entries.iter().any(|e| e.name == name) {
+                n += 1;
+                name = format!("{} {}", combatant.name, n);
+            }
+            if !combatant.defenses.qualified.is_empty() {
+                self.notices.push(format!(
+                    "{} has defenses that only hold against some attacks, not applied automatically: {}.",
+                    name,
+                    combatant.defenses.qualified.join("; ")
+                ));
+            }
+            self.add_entry(InitiativeTrackerEntry {
+                id: fastrand::u64(..),
+                name,
+                ..combatant.clone()
+            });
+        }
+    }
+
+    /// Removes a combatant from the fight.
+    fn remove_entry(&mut self, index: usize) {
+        let active_id = self.active_index.map(|i| self.entries[i].id);
+        let entry = self.entries.remove(index);
+        if active_id == Some(entry.id) {
+            // The turn passes to whoever moved up into the removed creature's place.
+            self.active_index = match self.entries.len() {
+                0 => None,
+                len => Some(self.turn_span(index.min(len - 1)).start),
+            };
+        } else {
+            self.restore_active(active_id);
+        }
+        self.log(CombatEvent::CombatantRemoved { name: entry.name });
+        self.prune_groups();
+    }
+
+    /// Writes the combat log to a file picked by the user, as Markdown or JSON.
+    fn export_combat_log(&self, as_json: bool) {
+        let (filter, extension) = if as_json {
+            ("JSON", "json")
+        } else {
+            ("Markdown", "md")
+        };
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter(filter, &[extension])
+            .set_file_name(format!("combat_log.{}", extension))
+            .save_file()
+        else {
+            return;
+        };
+        let contents = if as_json {
+            self.combat_log.to_json().map_err(Box::<dyn Error>::from)
+        } else {
+            Ok(self.combat_log.to_markdown())
+        };
+        if let Err(e) = contents.and_then(|text| Ok(std::fs::write(&path, text)?)) {
+            eprintln!("Failed to export combat log to {}: {}", path.display(), e);
+        }
+    }
+
+    /// The collapsible combat log panel along the bottom of the tracker.
+    fn combat_log_panel(&mut self, ctx: &egui::Context) {
+        egui::TopBottomPanel::bottom("combat_log")
+            .resizable(true)
+            .show(ctx, |ui| {
+                egui::CollapsingHeader::new("Combat Log")
+                    .default_open(false)
+                    .show(ui, |ui| {
+                        ui.horizontal(|ui| {
+                            if ui.button("Export Markdown").clicked() {
+                                self.export_combat_log(false);
+                            }
+                            if ui.button("Export JSON").clicked() {
+                                self.export_combat_log(true);
+                            }
+                            if ui.button("Clear").clicked() {
+                                self.combat_log.clear();
+                            }
+                        });
+                        egui::ScrollArea::vertical()
+                            .max_height(200.0)
+                            .stick_to_bottom(true)
+                            .show(ui, |ui| {
+                                for entry in self.combat_log.entries() {
+                                    ui.label(format!(
+                                        "{}  R{}  {}",
+                                        entry.time.format("%H:%M:%S"),
+                                        entry.round,
+                                        entry.event.describe()
+                                    ));
+                                }
+                            });
+                    });
+            });
+    }
+
+    /// Adds every PC from the active party roster as a new track.
+    fn add_pcs(&mut self) {
+        let Some(party) = self.settings.active_party() else {
+            eprintln!("No party configured; add one in Settings.");
+            return;
+        };
+        if party.path == default_party_path()
+            && !party.path.exists()
+            && let Err(e) = write_bundled_party(&party.path)
+        {
+            eprintln!(
+                "Failed to create party roster {}: {}",
+                party.path.display(),
+                e
+            );
+        }
+        match load_pcs_from_file(&party.path) {
+            Ok(pcs) => {
+                for pcs in pcs {
+                    // For each PC loaded, create a new InitiativeTrackerEntry
+                    // and push it to the entries vector.
+                    // We use the default values for initiative and conditions.
+                    self.add_entry(InitiativeTrackerEntry {
+                        name: pcs.name,
+                        hp_current: pcs.hp,
+                        hp_total: pcs.max_hp.unwrap_or(pcs.hp),
+                        hp_temp: pcs.temp_hp,
+                        armor_class: pcs.ac,
+                        initiative_modifier: pcs.initiative_modifier,
+                        passive_perception: pcs.passive_perception,
+                        is_pc: true,
+                        ..InitiativeTrackerEntry::default() // Use default values for the rest
+                    })
+                }
+            }
+            Err(e) => {
+                // If it fails, print the error to the console.
+                // A more advanced app might show a pop-up.
+                eprintln!("Failed to load PCs from {}: {}", party.path.display(), e);
+            }
+        }
+    }
+
+    /// Moves `path` to the front of the recent encounters list.
+    fn remember_encounter(&mut self, path: PathBuf) {
+        self.recent_encounters.retain(|p| p != &path);
+        self.recent_encounters.insert(0, path.clone());
+        self.recent_encounters.truncate(MAX_RECENT_ENCOUNTERS);
+        self.encounter_path = Some(path);
+    }
+
+    /// Saves the entries to `path` and makes it the current encounter file.
+    fn save_encounter(&mut self, path: PathBuf) {
+        match save_encounter_to_file(&path, &self.entries, &self.groups) {
+            Ok(()) => self.remember_encounter(path),
+            Err(e) => eprintln!("Failed to save encounter to {}: {}", path.display(), e),
+        }
+    }
+
+    /// Replaces the entries with the ones in `path` and starts the encounter fresh.
+    fn open_encounter(&mut self, path: PathBuf) {
+        match load_encounter_from_file(&path) {
+            Ok(file) => {
+                self.entries = file.entries;
+                self.groups = file.groups;
+                self.prune_groups();
+                self.active_index = None;
+                self.round_count = 1;
+                self.turn_steps.clear();
+                self.tie_groups.clear();
+                self.show_tie_breaker = false;
+                self.remember_encounter(path);
+            }
+            Err(e) => {
+                eprintln!("Failed to open encounter {}: {}", path.display(), e);
+                // Drop files that are gone so they don't linger in the recent list.
+                if !path.exists() {
+                    self.recent_encounters.retain(|p| p != &path);
+                }
+            }
+        }
+    }
+
+    /// A file dialog for encounter JSON files, starting next to the current file.
+    fn encounter_dialog(&self) -> rfd::FileDialog {
+        let directory = self
+            .encounter_path
+            .as_deref()
+            .and_then(Path::parent)
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| {
+                let directory = default_encounter_dir();
+                // So the dialog can open in it before the first save.
+                if let Err(e) = std::fs::create_dir_all(&directory) {
+                    eprintln!("Failed to create {}: {}", directory.display(), e);
+                }
+                directory
+            });
+        rfd::FileDialog::new()
+            .add_filter("Encounter", &["json"])
+            .set_directory(directory)
+    }
+
+    /// The "Encounter" menu with save, save-as, open and the recent files.
+    fn encounter_menu(&mut self, ui: &mut egui::Ui) {
+        ui.menu_button("Encounter", |ui| {
+            if ui.button("Save").clicked() {
+                let path = self
+                    .encounter_path
+                    .clone()
+                    .or_else(|| self.encounter_dialog().save_file());
+                if let Some(path) = path {
+                    self.save_encounter(path);
+                }
+                ui.close();
+            }
+            if ui.button("Save As...").clicked() {
+                if let Some(path) = self.encounter_dialog().save_file() {
+                    self.save_encounter(path);
+                }
+                ui.close();
+            }
+            if ui.button("Open...").clicked() {
+                if let Some(path) = self.encounter_dialog().pick_file() {
+                    self.open_encounter(path);
+                }
+                ui.close();
+            }
+            ui.separator();
+            ui.label("Recent:");
+            if self.recent_encounters.is_empty() {
+                ui.weak("No recent encounters");
+            }
+            // Clicking an entry reorders the list, so open it after the loop.
+            let mut path_to_open = None;
+            for path in &self.recent_encounters {
+                let label = path
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| path.display().to_string());
+                if ui
+                    .button(label)
+                    .on_hover_text(path.display().to_string())
+                    .clicked()
+                {
+                    path_to_open = Some(path.clone());
+                }
+            }
+            if let Some(path) = path_to_open {
+                self.open_encounter(path);
+                ui.close();
+            }
+        });
+    }
+
+    /// Writes the whole encounter to eframe's storage.
+    pub fn save_state(&self, storage: &mut dyn eframe::Storage) {
+        // A borrowing twin of `SavedTracker`, so saving doesn't clone every entry.
+        #[derive(Serialize)]
+        struct SavedTrackerRef<'a> {
+            version: u32,
+            tracker: &'a InitiativeTracker,
+        }
+        eframe::set_value(
+            storage,
+            STORAGE_KEY,
+            &SavedTrackerRef {
+                version: SAVE_VERSION,
+                tracker: self,
+            },
+        );
+    }
+
+    // "This is where we define how our app will behave when it is run."
+    pub fn update_ui(&mut self, ctx: &eframe::egui::Context) {
+        self.handle_undo_shortcuts(ctx);
+        self.handle_hotkeys(ctx);
+
+        if self.show_tie_breaker {
+            // This Area covers the whole screen and darkens it, creating a modal effect.
+            egui::Area::new(egui::Id::new("tie_breaker_modal_layer"))
+                .fixed_pos(egui::pos2(0.0, 0.0))
+                .order(egui::Order::Foreground) // Ensure it's drawn on top
+                .show(ctx, |ui| {
+                    // Now, draw the actual window in the center of the screen
+                    egui::Window::new("Resolve Initiative Tie")
+                        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+                        .collapsible(false)
+                        .resizable(false)
+                        .show(ui.ctx(), |ui| {
+                            ui.label("Click the arrows to re-order the combatants.");
+                            ui.separator();
+
+                            // The first unresolved tie, in its current order.
+                            // Drop it if its entries were removed in the meantime.
+                            let Some(group) = self.tie_groups.first().cloned() else {
+                                self.show_tie_breaker = false;
+                                return;
+                            };
+                            let tied_indices = self.indices_of(&group);
+                            if tied_indices.len() < 2 {
+                                self.tie_groups.remove(0);
+                                return;
+                            }
+
+                            // We must use a temporary variable for swapping to avoid borrowing issues
+                            let mut swap_indices = None;
+                            for i in 0..tied_indices.len() {
+                                ui.horizontal(|ui| {
+                                    if ui.add_enabled(i > 0, egui::Button::new("^")).clicked() {
+                                        // Mark indices to be swapped after the loop
+                                        swap_indices = Some((tied_indices[i], tied_indices[i - 1]));
+                                    }
+                                    if ui
+                                        .add_enabled(
+                                            i < tied_indices.len() - 1,
+                                            egui::Button::new("v"),
+                                        )
+                                        .clicked()
+                                    {
+                                        // Mark indices to be swapped after the loop
+                                        swap_indices = Some((tied_indices[i], tied_indices[i + 1]));
+                                    }
+                                    let real_index = tied_indices[i];
+                                    ui.label(self.turn_name(real_index));
+                                });
+                            }
+
+                            // Perform the swap outside the loop
+                            if let Some((index_a, index_b)) = swap_indices {
+                                self.swap_tie_break_ranks(index_a, index_b);
+                            }
+
+                            ui.separator();
+                            if ui.button("Confirm Order").clicked() {
+                                self.tie_groups.remove(0);
+                            }
+                        });
+                });
+            if self.tie_groups.is_empty() {
+                // If there are no tied initiatives, hide the tie breaker window
+                self.show_tie_breaker = false;
+            }
+        }
+
+        self.death_save_window(ctx);
+        self.concentration_window(ctx);
+
+        let mut show_bulk_add = self.show_bulk_add;
+        egui::Window::new("Add Multiple")
+            .open(&mut show_bulk_add)
+            .resizable(false)
+            .show(ctx, |ui| {
+                if self.bulk_add.ui(ui) && self.add_multiple() {
+                    self.show_bulk_add = false;
+                }
+            });
+        self.show_bulk_add &= show_bulk_add;
+
+        egui::Window::new("Settings")
+            .open(&mut self.show_settings)
+            .show(ctx, |ui| {
+                self.settings.settings_ui(ui);
+            });
+
+        egui::Window::new("Hotkeys")
+            .open(&mut self.show_cheatsheet)
+            .resizable(false)
+            .show(ctx, |ui| {
+                self.settings.keymap.cheatsheet_ui(ui);
+            });
+        self.find_window(ctx);
+
+        // Side and bottom panels have to be added before the central panel.
+        self.combat_log_panel(ctx);
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                self.encounter_menu(ui);
+                if ui.button("Settings").clicked() {
+                    self.show_settings = !self.show_settings;
+                }
+                ui.toggle_value(&mut self.show_player_view, "Player View")
+                    .on_hover_text("A second window for the players, without GM-only details");
+                let cheatsheet_hover = self
+                    .settings
+                    .keymap
+                    .describe(ctx, HotkeyAction::ShowCheatsheet);
+                ui.toggle_value(&mut self.show_cheatsheet, "Hotkeys")
+                    .on_hover_text(cheatsheet_hover);
+                if ui
+                    .add_enabled(self.history.can_undo(), egui::Button::new("⟲ Undo"))
+                    .on_hover_text("Ctrl+Z")
+                    .clicked()
+                {
+                    self.undo();
+                }
+                if ui
+                    .add_enabled(self.history.can_redo(), egui::Button::new("⟳ Redo"))
+                    .on_hover_text("Ctrl+Shift+Z or Ctrl+Y")
+                    .clicked()
+                {
+                    self.redo();
+                }
+                // Add the 'Add Track' button at the top
+                let add_track_hover = self.settings.keymap.describe(ctx, HotkeyAction::AddTrack);
+                if ui
+                    .button("Add New Track")
+                    .on_hover_text(add_track_hover)
+                    .clicked()
+                {
+                    self.add_entry(InitiativeTrackerEntry::default());
+                }
+                let add_pcs_hover = match self.settings.active_party() {
+                    Some(party) => format!("Add {} ({})", party.name, party.path.display()),
+                    None => "No party configured, see Settings".to_string(),
+                };
+                if ui
+                    .button("Add Multiple...")
+                    .on_hover_text("Add several copies of a creature")
+                    .clicked()
+                {
+                    self.show_bulk_add = true;
+                }
+                let has_lair = self.entries.iter().any(|e| e.lair_actions);
+                if ui
+                    .add_enabled(!has_lair, egui::Button::new("Add Lair"))
+                    .on_hover_text("Add a turn for lair actions on initiative count 20")
+                    .clicked()
+                {
+                    self.add_entry(InitiativeTrackerEntry::lair());
+                }
+                if ui.button("Add PCs").on_hover_text(add_pcs_hover).clicked() {
+                    self.add_pcs();
+                }
+                if self.settings.parties.len() > 1 {
+                    let selected = self
+                        .settings
+                        .active_party()
+                        .map(|party| party.name.clone())
+                        .unwrap_or_default();
+                    egui::ComboBox::from_id_salt("active_party")
+                        .selected_text(selected)
+                        .show_ui(ui, |ui| {
+                            for (i, party) in self.settings.parties.iter().enumerate() {
+                                ui.selectable_value(
+                                    &mut self.settings.active_party,
+                                    i,
+                                    &party.name,
+                                );
+                            }
+                        });
+                }
+                // Add the 'Sort by Initiative' button
+                let sort_hover = self.settings.keymap.describe(ctx, HotkeyAction::Sort);
+                if ui
+                    .button("Sort by Initiative")
+                    .on_hover_text(sort_hover)
+                    .clicked()
+                {
+                    self.sort_by_initiative();
+                }
+                ui.menu_button("Roll Initiative", |ui| {
+                    if ui
+                        .button("All")
+                        .on_hover_text("Roll d20 + modifier for everyone, then sort")
+                        .clicked()
+                    {
+                        self.roll_initiative(false);
+                        ui.close();
+                    }
+                    if ui
+                        .button("NPCs Only")
+                        .on_hover_text("Leave PC initiatives for the players to enter, then sort")
+                        .clicked()
+                    {
+                        self.roll_initiative(true);
+                        ui.close();
+                    }
+                });
+                // Add the 'Next Turn' button
+                let previous_turn_hover = self
+                    .settings
+                    .keymap
+                    .describe(ctx, HotkeyAction::PreviousTurn);
+                if ui
+                    .add_enabled(
+                        self.active_index.is_some(),
+                        egui::Button::new("Previous Turn"),
+                    )
+                    .on_hover_text(previous_turn_hover)
+                    .clicked()
+                {
+                    self.previous_turn();
+                }
+                let next_turn_hover = self.settings.keymap.describe(ctx, HotkeyAction::NextTurn);
+                if ui
+                    .button("Next Turn")
+                    .on_hover_text(next_turn_hover)
+                    .clicked()
+                {
+                    self.next_turn();
+                }
+                if ui.button("Reset Combat").clicked() {
+                    self.round_count = 1; // Reset the round count to 1
+                    self.active_index = None; // Reset the active index
+                    self.entries.clear(); // Clear all entries
+                    self.groups.clear();
+                    self.turn_steps.clear();
+                    self.tie_groups.clear();
+                    self.show_tie_breaker = false;
+                    self.death_save_prompt = None;
+                    self.concentration_checks.clear();
+                    self.notices.clear();
+                    self.log(CombatEvent::CombatReset);
+                    self.encounter_path = None; // So "Save" doesn't overwrite the file with an empty fight
+                }
+                // Add Round Counter
+                ui.with_layout(egui::Layout::top_down(egui::Align::Max), |ui| {
+                    ui.label(format!("Round: {}", self.round_count));
+                });
+            });
+            ui.separator();
+            if !self.notices.is_empty() {
+                for notice in &self.notices {
+                    ui.colored_label(egui::Color32::from_rgb(255, 200, 0), notice);
+                }
+                if ui.button("Dismiss").clicked() {
+                    self.notices.clear();
+                }
+                ui.separator();
+            }
+            self.legendary_actions_bar(ui);
+            let combatants: Vec<(u64, String)> = self
+                .entries
+                .iter()
+                .map(|e| (e.id, e.name.clone()))
+                .collect();
+            let concentrating: Vec<u64> = self
+                .entries
+                .iter()
+                .filter(|e| e.concentrating)
+                .map(|e| e.id)
+                .collect();
+            let active_span = self.active_index.map_or(0..0, |a| self.turn_span(a));
+            let delay_targets: Vec<(u64, String)> = self
+                .turn_starts()
+                .into_iter()
+                .filter(|&t| t >= active_span.end && active_span.len() == 1)
+                .map(|t| {
+                    let last = self.turn_span(t).end - 1;
+                    (self.entries[last].id, self.turn_name(t))
+                })
+                .collect();
+            let context = CardContext {
+                combatants: &combatants,
+                concentrating: &concentrating,
+                active_span,
+                delay_targets: &delay_targets,
+            };
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                let mut actions = CardActions::default();
+                for i in 0..self.entries.len() {
+                    self.entry_card(ui, i, &context, &mut actions);
+                }
+                if let Some(index) = actions.hp_update_index {
+                    self.apply_hp_update(index);
+                }
+                if let Some((index, result)) = actions.death_save {
+                    self.death_save(index, result);
+                }
+                if let Some(index) = actions.condition_to_add {
+                    self.add_condition(index);
+                }
+                if let Some(index) = actions.concentration_to_drop {
+                    self.drop_concentration(index);
+                }
+                if let Some(index) = actions.legendary_to_use {
+                    self.use_legendary_action(index);
+                }
+                if let Some(index) = actions.index_to_ready {
+                    self.ready_action(index);
+                }
+                if let Some(index) = actions.readied_to_trigger {
+                    self.trigger_readied_action(index);
+                }
+                // Last, since these move entries around.
+                if let Some(after_id) = actions.delay_after {
+                    self.delay_until_after(after_id);
+                }
+                if let Some((index, change)) = actions.group_change {
+                    self.change_group(index, change);
+                }
+                if let Some(group_id) = actions.group_to_ungroup {
+                    self.ungroup(group_id);
+                }
+                if let Some(index) = actions.index_to_reveal {
+                    self.reveal(index);
+                }
+                if let Some((moved_id, target_id, after)) = actions.entry_to_move {
+                    self.move_entry(moved_id, target_id, after);
+                }
+                if let Some(index) = actions.index_to_activate {
+                    self.make_active(index);
+                }
+                // Members follow their group's initiative as it's edited in the header.
+                self.sync_groups();
+                if let Some((index, condition)) = actions.condition_to_remove {
+                    self.remove_condition(index, condition);
+                }
+                if let Some(index) = actions.index_to_roll {
+                    self.roll_initiative_for(index);
+                }
+                // After the loop, remove the marked entry if any.
+                if let Some(index) = actions.index_to_remove {
+                    self.remove_entry(index);
+                }
+            }); // end of the scroll area
+        }); // end of the central panel
+
+        self.record_history(ctx);
+    } // end of the update function
+
+    /// One track's card, or its group's header and card. What the buttons
+    /// do is left in `actions`, to be applied once every card is drawn.
+    fn entry_card(
+        &mut self,
+        ui: &mut egui::Ui,
+        i: usize,
+        context: &CardContext,
+        actions: &mut CardActions,
+    ) {
+        let CardContext {
+            combatants,
+            concentrating,
+            active_span,
+            delay_targets,
+        } = context;
+        let is_active = active_span.contains(&i);
+        let stroke_color = if is_active {
+            egui::Color32::from_rgb(255, 0, 0) // Red for active combatant
+        } else {
+            egui::Color32::from_rgb(255, 255, 255) // White for inactive combatants
+        };
+        let stroke = egui::Stroke::new(1.0, stroke_color);
+
+        // A group's header goes above its first member.
+        let group_index = self.entries[i]
+            .group_id
+            .and_then(|id| self.groups.iter().position(|g| g.id == id));
+        if let Some(g) = group_index
+            && (i == 0 || self.entries[i - 1].group_id != self.entries[i].group_id)
+        {
+            let span = self.turn_span(i);
+            let leader_id = self.entries[i].id;
+            let header = egui::Frame::group(ui.style())
+                .stroke(stroke)
+                .show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        drag_handle(ui, leader_id);
+                        let group = &mut self.groups[g];
+                        let arrow = if group.collapsed { "▶" } else { "▼" };
+                        if ui
+                            .small_button(arrow)
+                            .on_hover_text("Show or hide the members' cards")
+                            .clicked()
+                        {
+                            group.collapsed = !group.collapsed;
+                        }
+                        ui.add(egui::TextEdit::singleline(&mut group.name).desired_width(140.0));
+                        ui.label(format!("{} members", span.len()));
+                        ui.separator();
+                        ui.label("Init:");
+                        if ui
+                            .add(egui::DragValue::new(&mut self.entries[i].initiative))
+                            .changed()
+                        {
+                            self.entries[i].clear_tie_break();
+                        }
+                        if ui
+                            .small_button("🎲")
+                            .on_hover_text("Roll initiative for the whole group")
+                            .clicked()
+                        {
+                            actions.index_to_roll = Some(i);
+                        }
+                        if ui
+                            .button("Ungroup")
+                            .on_hover_text("Give every member their own turn")
+                            .clicked()
+                        {
+                            actions.group_to_ungroup = Some(self.groups[g].id);
+                        }
+                        if ui
+                            .add_enabled(!is_active, egui::Button::new("Make Active"))
+                            .on_hover_text("Make it the group's turn")
+                            .clicked()
+                        {
+                            actions.index_to_activate = Some(i);
+                        }
+                    });
+                    if self.groups[g].collapsed {
+                        for member in &self.entries[span] {
+                            let mut summary = format!(
+                                "{}: {}/{} HP",
+                                member.name,
+                                member.hp_current,
+                                member.hp_max()
+                            );
+                            for condition in &member.conditions {
+                                summary.push_str(&format!(", {}", condition.kind.name()));
+                            }
+                            ui.label(summary);
+                        }
+                    }
+                });
+            // Below an open header is the group's own first card.
+            let collapsed = self.groups[g].collapsed;
+            if let Some((moved_id, after)) = drop_on_card(ui, &header.response, collapsed) {
+                actions.entry_to_move = Some((moved_id, leader_id, after));
+            }
+        }
+        if group_index.is_some_and(|g| self.groups[g].collapsed) {
+            return;
+        }
+
+        let card = egui::Frame::group(ui.style())
+            .stroke(stroke)
+            .show(ui, |ui| {
+                // You can keep the delete button separate, aligned to the right of the whole entry
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Min), |ui| {
+                    if ui.button("X").on_hover_text("Delete this entry").clicked() {
+                        actions.index_to_remove = Some(i);
+                    }
+                    drag_handle(ui, self.entries[i].id);
+                    if ui
+                        .add_enabled(!is_active, egui::Button::new("Make Active"))
+                        .on_hover_text("Make it this combatant's turn")
+                        .clicked()
+                    {
+                        actions.index_to_activate = Some(i);
+                    }
+                });
+                egui::Grid::new(format!("entry_grid_{}", i)) // Each grid needs a unique ID
+                    .num_columns(2)
+                    .spacing([10.0, 4.0]) // Add some spacing between columns and rows
+                    .show(ui, |ui| {
+                        // -- Row 1: Name --
+                        ui.label("Name:");
+                        let name = ui.add(
+                            egui::TextEdit::singleline(&mut self.entries[i].name)
+                                .desired_width(f32::INFINITY), // This makes the widget fill the available space
+                        );
+                        if self.card_focus == Some((self.entries[i].id, CardField::Name)) {
+                            name.request_focus();
+                            name.scroll_to_me(Some(egui::Align::Center));
+                            self.card_focus = None;
+                        }
+                        ui.end_row(); // Finish the first row
+
+                        // The lair only has a place in the order.
+                        if self.entries[i].lair_actions {
+                            ui.label("Lair:");
+                            ui.horizontal(|ui| {
+                                ui.label("Init:");
+                                if ui
+                                    .add(egui::DragValue::new(&mut self.entries[i].initiative))
+                                    .changed()
+                                {
+                                    self.entries[i].clear_tie_break();
+                                }
+                                ui.weak("Lair actions lose initiative ties");
+                            });
+                            ui.end_row();
+                            return;
+                        }
+
+                        // -- Row 2: Initiative & HP --
+                        ui.label("Stats:");
+                        ui.horizontal(|ui| {
+                            if self.entries[i].group_id.is_some() {
+                                ui.label(format!("Init: {} (group)", self.entries[i].initiative));
+                            } else {
+                                ui.label("Init:");
+                                if ui
+                                    .add(egui::DragValue::new(&mut self.entries[i].initiative))
+                                    .changed()
+                                {
+                                    self.entries[i].clear_tie_break();
+                                }
+                                if ui
+                                    .small_button("🎲")
+                                    .on_hover_text("Roll initiative for this combatant")
+                                    .clicked()
+                                {
+                                    actions.index_to_roll = Some(i);
+                                }
+                                let roll_mode = &mut self.entries[i].initiative_roll_mode;
+                                egui::ComboBox::from_id_salt(format!("roll_mode_{}", i))
+                                    .width(60.0)
+                                    .selected_text(roll_mode.label())
+                                    .show_ui(ui, |ui| {
+                                        for mode in [
+                                            RollMode::Normal,
+                                            RollMode::Advantage,
+                                            RollMode::Disadvantage,
+                                        ] {
+                                            ui.selectable_value(roll_mode, mode, mode.label());
+                                        }
+                                    })
+                                    .response
+                                    .on_hover_text("Advantage or disadvantage on initiative");
+                            }
+
+                            ui.separator(); // A small vertical line
+
+                            ui.label("HP:");
+                            // A DragValue for the current HP
+                            ui.add(egui::DragValue::new(&mut self.entries[i].hp_current));
+                            ui.label("/");
+                            // A new DragValue for the total HP, making it editable
+                            ui.add(egui::DragValue::new(&mut self.entries[i].hp_total));
+                            let entry = &mut self.entries[i];
+                            if entry.hp_max_reduction > 0 {
+                                ui.label(format!("(max {})", entry.hp_max()));
+                            }
+                            ui.label("Temp:");
+                            ui.add(egui::DragValue::new(&mut entry.hp_temp).range(0..=i32::MAX));
+                            ui.label("Max −:");
+                            if ui
+                                .add(
+                                    egui::DragValue::new(&mut entry.hp_max_reduction)
+                                        .range(0..=i32::MAX),
+                                )
+                                .on_hover_text("Max HP reduction, e.g. from Life Drain")
+                                .changed()
+                            {
+                                entry.hp_current = entry.hp_current.min(entry.hp_max());
+                            }
+                        });
+                        ui.end_row();
+
+                        // -- Row 3: Roster Stats --
+                        ui.label("Details:");
+                        ui.horizontal(|ui| {
+                            ui.label("AC:");
+                            ui.add(egui::DragValue::new(&mut self.entries[i].armor_class));
+                            ui.separator();
+                            ui.label("Init Mod:");
+                            ui.add(
+                                egui::DragValue::new(&mut self.entries[i].initiative_modifier)
+                                    .custom_formatter(|n, _| format!("{:+}", n)),
+                            );
+                            ui.separator();
+                            ui.label("PP:").on_hover_text("Passive Perception");
+                            ui.add(egui::DragValue::new(
+                                &mut self.entries[i].passive_perception,
+                            ));
+                            ui.separator();
+                            ui.checkbox(&mut self.entries[i].is_pc, "PC");
+                            ui.checkbox(&mut self.entries[i].surprised, "Surprised")
+                                .on_hover_text("Skipped by Next Turn in round 1");
+                            if self.entries[i].hidden {
+                                if ui
+                                    .button("Reveal")
+                                    .on_hover_text("Show in the Player View, in initiative order")
+                                    .clicked()
+                                {
+                                    actions.index_to_reveal = Some(i);
+                                }
+                            } else {
+                                ui.checkbox(&mut self.entries[i].hidden, "Hidden")
+                                    .on_hover_text("Leave out of the Player View");
+                            }
+                            ui.separator();
+                            ui.label("Group:");
+                            let current = self.entries[i].group_id;
+                            let current_name = current
+                                .and_then(|id| self.groups.iter().find(|g| g.id == id))
+                                .map_or("None", |group| group.name.as_str());
+                            egui::ComboBox::from_id_salt(format!("group_{}", i))
+                                .width(90.0)
+                                .selected_text(current_name)
+                                .show_ui(ui, |ui| {
+                                    if current.is_some() && ui.button("None").clicked() {
+                                        actions.group_change = Some((i, GroupChange::Leave));
+                                    }
+                                    for group in
+                                        self.groups.iter().filter(|g| Some(g.id) != current)
+                                    {
+                                        if ui.button(&group.name).clicked() {
+                                            actions.group_change =
+                                                Some((i, GroupChange::Join(group.id)));
+                                        }
+                                    }
+                                    if ui.button("New group").clicked() {
+                                        actions.group_change = Some((i, GroupChange::New));
+                                    }
+                                })
+                                .response
+                                .on_hover_text(
+                                    "Grouped creatures share one initiative and one turn",
+                                );
+                            ui.separator();
+                            let entry = &mut self.entries[i];
+                            ui.label("Legendary:");
+                            if ui
+                                .add(
+                                    egui::DragValue::new(&mut entry.legendary_actions)
+                                        .range(0..=10),
+                                )
+                                .on_hover_text("Legendary actions per round")
+                                .changed()
+                            {
+                                entry.legendary_actions_left = entry.legendary_actions;
+                            }
+                            if entry.legendary_actions > 0 {
+                                ui.label(format!(
+                                    "{}/{} left",
+                                    entry.legendary_actions_left, entry.legendary_actions
+                                ));
+                                if ui
+                                    .add_enabled(
+                                        entry.legendary_actions_left > 0 && !is_active,
+                                        egui::Button::new("Use").small(),
+                                    )
+                                    .on_hover_text(
+                                        "Use a legendary action after another creature's turn",
+                                    )
+                                    .clicked()
+                                {
+                                    actions.legendary_to_use = Some(i);
+                                }
+                            }
+                        });
+                        ui.end_row();
+
+                        // -- Concentration --
+                        ui.label("Concentration:");
+                        ui.horizontal(|ui| {
+                            let entry = &mut self.entries[i];
+                            let mut concentrating = entry.concentrating;
+                            if ui.checkbox(&mut concentrating, "").changed() {
+                                if concentrating {
+                                    entry.concentrating = true;
+                                } else {
+                                    actions.concentration_to_drop = Some(i);
+                                }
+                            }
+                            ui.add_enabled(
+                                entry.concentrating,
+                                egui::TextEdit::singleline(&mut entry.concentration_spell)
+                                    .hint_text("Spell")
+                                    .desired_width(120.0),
+                            );
+                            ui.label("Con save:");
+                            ui.add(
+                                egui::DragValue::new(&mut entry.con_save_modifier)
+                                    .custom_formatter(|n, _| format!("{:+}", n)),
+                            );
+                        });
+                        ui.end_row();
+
+                        // -- Delay & Ready, on the active combatant's turn --
+                        let entry = &self.entries[i];
+                        if is_active || entry.readied_action.is_some() {
+                            ui.label("Turn:");
+                            ui.horizontal(|ui| {
+                                if is_active && self.entries[i].group_id.is_none() {
+                                    let later = *delay_targets;
+                                    ui.add_enabled_ui(!later.is_empty(), |ui| {
+                                        ui.menu_button("Delay", |ui| {
+                                            ui.label("Act after:");
+                                            for (id, name) in later {
+                                                if ui.button(name).clicked() {
+                                                    actions.delay_after = Some(*id);
+                                                    ui.close();
+                                                }
+                                            }
+                                        })
+                                    })
+                                    .response
+                                    .on_hover_text("Move to a later point in this round");
+                                }
+                                let entry = &mut self.entries[i];
+                                match &entry.readied_action {
+                                    Some(trigger) => {
+                                        ui.label(format!("Readied: {}", trigger));
+                                        if ui
+                                            .small_button("Trigger")
+                                            .on_hover_text("Use the readied action")
+                                            .clicked()
+                                        {
+                                            actions.readied_to_trigger = Some(i);
+                                        }
+                                    }
+                                    None => {
+                                        ui.add(
+                                            egui::TextEdit::singleline(&mut entry.ready_form)
+                                                .hint_text("Trigger")
+                                                .desired_width(160.0),
+                                        );
+                                        if ui
+                                            .button("Ready")
+                                            .on_hover_text(
+                                                "Hold an action until the trigger happens",
+                                            )
+                                            .clicked()
+                                        {
+                                            actions.index_to_ready = Some(i);
+                                        }
+                                    }
+                                }
+                            });
+                            ui.end_row();
+                        }
+
+                        // -- Row 4: HP Update Form --
+                        ui.label("Damage/Heal:");
+                        ui.horizontal(|ui| {
+                            let amount = ui.add(
+                                egui::DragValue::new(&mut self.entries[i].hp_update)
+                                    .range(0..=i32::MAX),
+                            );
+                            if self.card_focus == Some((self.entries[i].id, CardField::Damage)) {
+                                amount.request_focus();
+                                amount.scroll_to_me(Some(egui::Align::Center));
+                                self.card_focus = None;
+                            }
+                            // Typing an amount and pressing Enter applies it.
+                            if amount.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter))
+                            {
+                                actions.hp_update_index = Some(i);
+                            }
+                            let kind = &mut self.entries[i].hp_update_kind;
+                            ui.radio_value(kind, HpUpdateKind::Damage, "−") // Using a proper minus sign
+                                .on_hover_text("Damage: temp HP is lost first");
+                            ui.radio_value(kind, HpUpdateKind::Heal, "+")
+                                .on_hover_text("Heal up to max HP");
+                            ui.radio_value(kind, HpUpdateKind::TempHp, "Temp")
+                                .on_hover_text(
+                                    "Grant temp HP: replaces the current pool if higher",
+                                );
+                            if *kind == HpUpdateKind::Damage {
+                                let damage_type = &mut self.entries[i].hp_update_damage_type;
+                                egui::ComboBox::from_id_salt(format!("damage_type_{}", i))
+                                    .width(100.0)
+                                    .selected_text(damage_type.map_or("untyped", DamageType::name))
+                                    .show_ui(ui, |ui| {
+                                        ui.selectable_value(damage_type, None, "untyped");
+                                        for kind in DamageType::ALL {
+                                            ui.selectable_value(
+                                                damage_type,
+                                                Some(kind),
+                                                kind.name(),
+                                            );
+                                        }
+                                    });
+                                ui.checkbox(&mut self.entries[i].hp_update_critical, "Crit")
+                                    .on_hover_text("Critical hit: two death save failures at 0 HP");
+                            }
+                            if ui.button("Update").clicked() {
+                                actions.hp_update_index = Some(i);
+                            }
+                            if let Some(result) = &self.entries[i].last_hp_update {
+                                ui.weak(result);
+                            }
+                        });
+                        ui.end_row();
+
+                        // -- Death saves, for PCs who are down --
+                        let entry = &self.entries[i];
+                        if entry.is_pc && (entry.hp_current == 0 || entry.death_saves.dead) {
+                            ui.label("Death Saves:");
+                            ui.horizontal(|ui| {
+                                let saves = &entry.death_saves;
+                                ui.label(format!(
+                                    "✓ {}  ✗ {}",
+                                    DeathSaves::pips(saves.successes),
+                                    DeathSaves::pips(saves.failures)
+                                ));
+                                ui.strong(saves.status());
+                                if entry.is_dying() {
+                                    if ui.small_button("Roll").clicked() {
+                                        actions.death_save = Some((i, None));
+                                    }
+                                    if ui.small_button("+✓").clicked() {
+                                        actions.death_save =
+                                            Some((i, Some(DeathSaveResult::Success)));
+                                    }
+                                    if ui.small_button("+✗").clicked() {
+                                        actions.death_save =
+                                            Some((i, Some(DeathSaveResult::Failure)));
+                                    }
+                                }
+                            });
+                            ui.end_row();
+                        }
+
+                        // -- Row 5: Damage Defenses --
+                        ui.label("Defenses:");
+                        ui.horizontal(|ui| {
+                            let defenses = &mut self.entries[i].defenses;
+                            defense_menu(ui, "Resist", &mut defenses.resistances);
+                            defense_menu(ui, "Immune", &mut defenses.immunities);
+                            defense_menu(ui, "Vuln", &mut defenses.vulnerabilities);
+                            ui.label(defenses.summary());
+                            for qualified in &defenses.qualified {
+                                ui.weak(qualified)
+                                    .on_hover_text("Only against some attacks: apply it by hand");
+                            }
+                        });
+                        ui.end_row();
+
+                        // -- Row 6: Conditions --
+                        ui.label("Conditions:");
+                        let descriptions: Vec<String> = self.entries[i]
+                            .conditions
+                            .iter()
+                            .map(|c| self.describe_condition(i, c))
+                            .collect();
+                        ui.vertical(|ui| {
+                            ui.horizontal_wrapped(|ui| {
+                                for (c, description) in descriptions.iter().enumerate() {
+                                    ui.label(description);
+                                    if ui
+                                        .small_button("x")
+                                        .on_hover_text("Remove this condition")
+                                        .clicked()
+                                    {
+                                        actions.condition_to_remove = Some((i, c));
+                                    }
+                                }
+                            });
+                            let entry_id = self.entries[i].id;
+                            let form = &mut self.entries[i].condition_form;
+                            ui.horizontal(|ui| {
+                                condition_form_ui(ui, i, entry_id, form, combatants, concentrating);
+                                if ui.button("Add").clicked() {
+                                    actions.condition_to_add = Some(i);
+                                }
+                            });
+                        });
+                        ui.end_row();
+                    });
+            }); // end of the frame for each entry
+        if let Some((moved_id, after)) = drop_on_card(ui, &card.response, true) {
+            actions.entry_to_move = Some((moved_id, self.entries[i].id, after));
+        }
+    }
+} // end of the App trait implementation
+
+/// What every card needs to know about the rest of the tracker.
+struct CardContext<'a> {
+    /// Everyone who can be picked as the source of a condition.
+    combatants: &'a [(u64, String)],
+    concentrating: &'a [u64],
+    active_span: Range<usize>,
+    /// The turns the active combatant can delay until after: (last member, turn name).
+    delay_targets: &'a [(u64, String)],
+}
+
+/// The buttons clicked on the cards this frame, applied after they are all drawn.
+#[derive(Default)]
+struct CardActions {
+    index_to_remove: Option<usize>,
+    index_to_roll: Option<usize>,
+    hp_update_index: Option<usize>,
+    death_save: Option<(usize, Option<DeathSaveResult>)>,
+    condition_to_add: Option<usize>,
+    concentration_to_drop: Option<usize>,
+    legendary_to_use: Option<usize>,
+    delay_after: Option<u64>,
+    index_to_ready: Option<usize>,
+    readied_to_trigger: Option<usize>,
+    condition_to_remove: Option<(usize, usize)>,
+    group_change: Option<(usize, GroupChange)>,
+    group_to_ungroup: Option<u64>,
+    index_to_reveal: Option<usize>,
+    /// (dragged entry id, entry id it was dropped on, whether it goes after).
+    entry_to_move: Option<(u64, u64, bool)>,
+    index_to_activate: Option<usize>,
+}
+
+/// The inputs for adding a condition to the entry at `index`.
+fn condition_form_ui(
+    ui: &mut egui::Ui,
+    index: usize,
+    entry_id: u64,
+    form: &mut ConditionForm,
+    combatants: &[(u64, String)],
+    concentrating: &[u64],
+) {
+    egui::ComboBox::from_id_salt(format!("condition_kind_{}", index))
+        .width(110.0)
+        .selected_text(match &form.kind {
+            ConditionKind::Custom(_) => "Custom...",
+            kind => kind.name(),
+        })
+        .show_ui(ui, |ui| {
+            for kind in ConditionKind::SRD {
+                let name = kind.name().to_string();
+                ui.selectable_value(&mut form.kind, kind, name);
+            }
+            ui.selectable_value(
+                &mut form.kind,
+                ConditionKind::Custom(String::new()),
+                "Custom...",
+            );
+        });
+    if matches!(form.kind, ConditionKind::Custom(_)) {
+        ui.add(
+            egui::TextEdit::singleline(&mut form.custom_name)
+                .hint_text("Name")
+                .desired_width(90.0),
+        );
+    }
+    ui.checkbox(&mut form.timed, "for");
+    ui.add_enabled(
+        form.timed,
+        egui::DragValue::new(&mut form.rounds)
+            .range(1..=100)
+            .suffix(" rds"),
+    );
+    if form.timed {
+        ui.label("ending at");
+        egui::ComboBox::from_id_salt(format!("condition_ends_at_{}", index))
+            .width(50.0)
+            .selected_text(form.ends_at.label())
+            .show_ui(ui, |ui| {
+                for boundary in [TurnBoundary::Start, TurnBoundary::End] {
+                    ui.selectable_value(&mut form.ends_at, boundary, boundary.label());
+                }
+            });
+        ui.label("of");
+        let source_name = form
+            .source_id
+            .and_then(|id| combatants.iter().find(|(cid, _)| *cid == id))
+            .map(|(_, name)| format!("{}'s", name))
+            .unwrap_or_else(|| "own".to_string());
+        egui::ComboBox::from_id_salt(format!("condition_source_{}", index))
+            .width(90.0)
+            .selected_text(source_name)
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut form.source_id, None, "own");
+                for (id, name) in combatants.iter().filter(|(id, _)| *id != entry_id) {
+                    ui.selectable_value(&mut form.source_id, Some(*id), format!("{}'s", name));
+                }
+            });
+        ui.label("turn");
+    }
+    // Only combatants who are concentrating can maintain a condition.
+    let concentrators: Vec<&(u64, String)> = combatants
+        .iter()
+        .filter(|(id, _)| concentrating.contains(id))
+        .collect();
+    if !concentrators.is_empty() {
+        let concentration_name = form
+            .concentration_id
+            .and_then(|id| concentrators.iter().find(|(cid, _)| *cid == id))
+            .map(|(_, name)| format!("{}'s concentration", name))
+            .unwrap_or_else(|| "no concentration".to_string());
+        egui::ComboBox::from_id_salt(format!("condition_concentration_{}", index))
+            .width(120.0)
+            .selected_text(concentration_name)
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut form.concentration_id, None, "no concentration");
+                for (id, name) in &concentrators {
+                    ui.selectable_value(
+                        &mut form.concentration_id,
+                        Some(*id),
+                        format!("{}'s concentration", name),
+                    );
+                }
+            })
+            .response
+            .on_hover_text("Ends when they lose concentration");
+    }
+}
+
+/// A menu of checkboxes for picking a set of damage types.
+fn defense_menu(ui: &mut egui::Ui, label: &str, set: &mut BTreeSet<DamageType>) {
+    ui.menu_button(label, |ui| {
+        for kind in DamageType::ALL {
+            let mut checked = set.contains(&kind);
+            if ui.checkbox(&mut checked, kind.name()).changed() {
+                if checked {
+                    set.insert(kind);
+                } else {
+                    set.remove(&kind);
+                }
+            }
+        }
+    });
+}
+
+/// A handle that drags the card of the entry with `entry_id`.
+fn drag_handle(ui: &mut egui::Ui, entry_id: u64) {
+    ui.dnd_drag_source(egui::Id::new(("drag_entry", entry_id)), entry_id, |ui| {
+        ui.label("☰");
+    })
+    .response
+    .on_hover_text("Drag to move this entry in the order");
+}
+
+/// Makes a card a drop target for dragged entries. Marks where a dragged entry
+/// would land and returns the dropped entry's id and whether it goes after the card.
+/// With `allow_after` false, entries always land before the card.
+fn drop_on_card(ui: &egui::Ui, card: &egui::Response, allow_after: bool) -> Option<(u64, bool)> {
+    let pointer = ui.ctx().pointer_interact_pos()?;
+    let after = allow_after && pointer.y > card.rect.center().y;
+    if card.dnd_hover_payload::<u64>().is_some() {
+        let y = if after {
+            card.rect.bottom()
+        } else {
+            card.rect.top()
+        };
+        let stroke = ui.visuals().selection.stroke;
+        ui.painter().hline(card.rect.x_range(), y, stroke);
+    }
+    let dropped = card.dnd_release_payload::<u64>()?;
+    Some((*dropped, after))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes `contents` to a file of its own in the temp dir.
+    fn temp_file(name: &str, contents: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "ttrpg_app_{}_{}_{}.json",
+            name,
+            std::process::id(),
+            fastrand::u64(..)
+        ));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn loads_minimal_roster_with_defaults() {
+        let path = temp_file("minimal", r#"[{"name": "Diana", "hp": 12}]"#);
+        let pcs = load_pcs_from_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(pcs.len(), 1);
+        let pc = &pcs[0];
+        assert_eq!(pc.name, "Diana");
+        assert_eq!(pc.hp, 12);
+        assert_eq!(pc.max_hp, None);
+        assert_eq!(pc.temp_hp, 0);
+        assert_eq!(pc.ac, 10);
+        assert_eq!(pc.initiative_modifier, 0);
+        assert_eq!(pc.passive_perception, 10);
+    }
+
+    #[test]
+    fn loads_full_roster() {
+        let path = temp_file(
+            "full",
+            r#"[{"name": "Rumi", "hp": 20, "max_hp": 25, "temp_hp": 3, "ac": 16,
+                "initiative_modifier": 2, "passive_perception": 14}]"#,
+        );
+        let pcs = load_pcs_from_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let pc = &pcs[0];
+        assert_eq!(pc.max_hp, Some(25));
+        assert_eq!(pc.temp_hp, 3);
+        assert_eq!(pc.ac, 16);
+        assert_eq!(pc.initiative_modifier, 2);
+        assert_eq!(pc.passive_perception, 14);
+    }
+
+    #[test]
+    fn bundled_roster_parses() {
+        let pcs: Vec<PCInfo> = serde_json::from_str(BUNDLED_PARTY).unwrap();
+        assert!(!pcs.is_empty());
+    }
+
+    fn entry_with_hp(hp: i32, temp: i32) -> InitiativeTrackerEntry {
+        InitiativeTrackerEntry {
+            hp_current: hp,
+            hp_total: hp,
+            hp_temp: temp,
+            ..InitiativeTrackerEntry::default()
+        }
+    }
+
+    #[test]
+    fn temp_hp_absorbs_damage_first() {
+        let mut entry = entry_with_hp(20, 5);
+        entry.take_damage(3, false);
+        assert_eq!((entry.hp_temp, entry.hp_current), (2, 20));
+        entry.take_damage(7, false);
+        assert_eq!((entry.hp_temp, entry.hp_current), (0, 15));
+        entry.take_damage(30, false);
+        assert_eq!(entry.hp_current, 0);
+    }
+
+    #[test]
+    fn temp_hp_keeps_the_larger_pool() {
+        let mut entry = entry_with_hp(20, 5);
+        entry.grant_temp_hp(3);
+        assert_eq!(entry.hp_temp, 5);
+        entry.grant_temp_hp(8);
+        assert_eq!(entry.hp_temp, 8);
+        // Healing doesn't add temp HP or go past the max.
+        entry.take_damage(12, false);
+        entry.heal(50);
+        assert_eq!((entry.hp_temp, entry.hp_current), (0, 20));
+    }
+
+    #[test]
+    fn max_hp_reduction_caps_healing() {
+        let mut entry = entry_with_hp(20, 0);
+        entry.hp_max_reduction = 6;
+        entry.take_damage(10, false);
+        entry.heal(50);
+        assert_eq!(entry.hp_current, 14);
+    }
+
+    #[test]
+    fn damage_at_0_hp_fails_death_saves() {
+        let mut pc = InitiativeTrackerEntry {
+            is_pc: true,
+            ..entry_with_hp(10, 0)
+        };
+        pc.take_damage(10, false);
+        assert!(pc.is_dying());
+        pc.take_damage(1, false);
+        assert_eq!(pc.death_saves.failures, 1);
+        pc.take_damage(1, true);
+        assert!(pc.death_saves.dead);
+    }
+
+    #[test]
+    fn massive_damage_kills_outright() {
+        let mut pc = InitiativeTrackerEntry {
+            is_pc: true,
+            ..entry_with_hp(10, 0)
+        };
+        pc.take_damage(19, false);
+        assert!(!pc.death_saves.dead);
+        pc.heal(10);
+        pc.take_damage(20, false);
+        assert!(pc.death_saves.dead);
+    }
+
+    /// A tracker with one entry per `(name, initiative, initiative modifier)`, sorted.
+    fn sorted_tracker(entries: &[(&str, i32, i32)]) -> InitiativeTracker {
+        let mut tracker = InitiativeTracker::default();
+        for &(name, initiative, initiative_modifier) in entries {
+            tracker.add_entry(InitiativeTrackerEntry {
+                name: name.to_string(),
+                initiative,
+                initiative_modifier,
+                ..InitiativeTrackerEntry::default()
+            });
+        }
+        tracker.sort_by_initiative();
+        tracker
+    }
+
+    fn names(tracker: &InitiativeTracker) -> Vec<&str> {
+        tracker.entries.iter().map(|e| e.name.as_str()).collect()
+    }
+
+    fn active_name(tracker: &InitiativeTracker) -> &str {
+        &tracker.entries[tracker.active_index.unwrap()].name
+    }
+
+    fn id_of(tracker: &InitiativeTracker, name: &str) -> u64 {
+        tracker.entries.iter().find(|e| e.name == name).unwrap().id
+    }
+
+    fn index_of(tracker: &InitiativeTracker, name: &str) -> usize {
+        tracker.entries.iter().position(|e| e.name == name).unwrap()
+    }
+
+    /// Gives the entry a condition lasting `rounds` of its own turns.
+    fn add_timed_condition(
+        tracker: &mut InitiativeTracker,
+        name: &str,
+        kind: &str,
+        rounds: u32,
+        ends_at: TurnBoundary,
+    ) {
+        let index = index_of(tracker, name);
+        tracker.entries[index].conditions.push(Condition {
+            rounds_remaining: Some(rounds),
+            ends_at,
+            ..Condition::indefinite(ConditionKind::from_name(kind))
+        });
+    }
+
+    fn rounds_left(tracker: &InitiativeTracker, name: &str, kind: &str) -> Option<u32> {
+        tracker.entries[index_of(tracker, name)]
+            .conditions
+            .iter()
+            .find(|c| c.kind.name() == kind)
+            .and_then(|c| c.rounds_remaining)
+    }
+
+    fn condition_names<'a>(tracker: &'a InitiativeTracker, name: &str) -> Vec<&'a str> {
+        tracker.entries[index_of(tracker, name)]
+            .conditions
+            .iter()
+            .map(|c| c.kind.name())
+            .collect()
+    }
+
+    #[test]
+    fn delaying_ends_the_turn_once_and_does_not_restart_it() {
+        let mut tracker = sorted_tracker(&[("A", 20, 0), ("B", 15, 0), ("C", 10, 0)]);
+        tracker.next_turn();
+        add_timed_condition(&mut tracker, "A", "Blessed", 5, TurnBoundary::Start);
+        add_timed_condition(&mut tracker, "A", "Hasted", 5, TurnBoundary::End);
+
+        tracker.delay_until_after(id_of(&tracker, "B"));
+        assert_eq!(names(&tracker), ["B", "A", "C"]);
+        assert_eq!(active_name(&tracker), "B");
+        assert_eq!(rounds_left(&tracker, "A", "Hasted"), Some(4));
+
+        tracker.next_turn();
+        assert_eq!(active_name(&tracker), "A");
+        tracker.next_turn();
+        assert_eq!(active_name(&tracker), "C");
+        // Neither a second start nor a second end of A's turn this round.
+        assert_eq!(rounds_left(&tracker, "A", "Blessed"), Some(5));
+        assert_eq!(rounds_left(&tracker, "A", "Hasted"), Some(4));
+        let a_turns = tracker
+            .combat_log
+            .entries()
+            .iter()
+            .filter(|e| matches!(&e.event, CombatEvent::TurnStarted { name } if name == "A"))
+            .count();
+        assert_eq!(a_turns, 1);
+
+        // Next round A's turn counts as usual.
+        tracker.next_turn();
+        tracker.next_turn();
+        assert_eq!(active_name(&tracker), "A");
+        assert_eq!(rounds_left(&tracker, "A", "Blessed"), Some(4));
+    }
+
+    #[test]
+    fn resorting_keeps_a_delay_over_the_tie_break_rules() {
+        // The higher modifier would put A first on a tie.
+        let mut tracker = sorted_tracker(&[("A", 15, 5), ("B", 15, 1), ("C", 10, 0)]);
+        assert_eq!(names(&tracker), ["A", "B", "C"]);
+        tracker.next_turn();
+        tracker.delay_until_after(id_of(&tracker, "B"));
+        assert_eq!(names(&tracker), ["B", "A", "C"]);
+        tracker.sort_by_initiative();
+        assert_eq!(names(&tracker), ["B", "A", "C"]);
+        assert!(!tracker.show_tie_breaker);
+    }
+
+    /// Resolves the first open tie by moving its second entry to the front.
+    fn put_second_first(tracker: &mut InitiativeTracker) {
+        let group = tracker.tie_groups.remove(0);
+        let tied = tracker.indices_of(&group);
+        tracker.swap_tie_break_ranks(tied[0], tied[1]);
+        tracker.show_tie_breaker = !tracker.tie_groups.is_empty();
+    }
+
+    #[test]
+    fn a_resolved_tie_survives_resorting() {
+        let mut tracker = sorted_tracker(&[("A", 15, 2), ("B", 15, 2), ("C", 10, 0)]);
+        assert_eq!(tracker.tie_groups.len(), 1);
+        put_second_first(&mut tracker);
+        assert_eq!(names(&tracker), ["B", "A", "C"]);
+
+        tracker.sort_by_initiative();
+        assert_eq!(names(&tracker), ["B", "A", "C"]);
+        assert!(tracker.tie_groups.is_empty());
+        assert!(!tracker.show_tie_breaker);
+    }
+
+    #[test]
+    fn a_late_joiner_keeps_the_resolved_order() {
+        let mut tracker = sorted_tracker(&[("A", 15, 2), ("B", 15, 2), ("C", 10, 0)]);
+        put_second_first(&mut tracker);
+
+        // Tied with both on every rule: asked about, but after the settled pair.
+        tracker.entries.push(InitiativeTrackerEntry {
+            name: "D".to_string(),
+            initiative: 15,
+            initiative_modifier: 2,
+            ..InitiativeTrackerEntry::default()
+        });
+        tracker.sort_by_initiative();
+        assert_eq!(names(&tracker), ["B", "A", "D", "C"]);
+        assert_eq!(tracker.tie_groups.len(), 1);
+
+        // Separated by the rules: no question, and the pair stays together.
+        tracker.entries.push(InitiativeTrackerEntry {
+            name: "E".to_string(),
+            initiative: 15,
+            initiative_modifier: 5,
+            ..InitiativeTrackerEntry::default()
+        });
+        tracker.sort_by_initiative();
+        assert_eq!(names(&tracker)[..3], ["E", "B", "A"]);
+    }
+
+    #[test]
+    fn moves_entries_to_either_end_of_the_list() {
+        let mut tracker = sorted_tracker(&[("A", 20, 0), ("B", 15, 0), ("C", 10, 0)]);
+        tracker.move_entry(id_of(&tracker, "C"), id_of(&tracker, "A"), false);
+        assert_eq!(names(&tracker), ["C", "A", "B"]);
+        tracker.move_entry(id_of(&tracker, "C"), id_of(&tracker, "B"), true);
+        assert_eq!(names(&tracker), ["A", "B", "C"]);
+        tracker.move_entry(id_of(&tracker, "A"), id_of(&tracker, "C"), true);
+        assert_eq!(names(&tracker), ["B", "C", "A"]);
+        tracker.move_entry(id_of(&tracker, "A"), id_of(&tracker, "B"), false);
+        assert_eq!(names(&tracker), ["A", "B", "C"]);
+        // Dropping an entry on itself leaves it where it is.
+        tracker.move_entry(id_of(&tracker, "B"), id_of(&tracker, "B"), true);
+        assert_eq!(names(&tracker), ["A", "B", "C"]);
+    }
+
+    #[test]
+    fn the_active_creature_stays_active_after_a_move() {
+        let mut tracker = sorted_tracker(&[("A", 20, 0), ("B", 15, 0), ("C", 10, 0)]);
+        tracker.active_index = Some(index_of(&tracker, "B"));
+        tracker.move_entry(id_of(&tracker, "A"), id_of(&tracker, "C"), true);
+        assert_eq!(names(&tracker), ["B", "C", "A"]);
+        assert_eq!(active_name(&tracker), "B");
+        tracker.move_entry(id_of(&tracker, "C"), id_of(&tracker, "B"), false);
+        assert_eq!(names(&tracker), ["C", "B", "A"]);
+        assert_eq!(active_name(&tracker), "B");
+        // Moving the active creature itself.
+        tracker.move_entry(id_of(&tracker, "B"), id_of(&tracker, "A"), true);
+        assert_eq!(names(&tracker), ["C", "A", "B"]);
+        assert_eq!(active_name(&tracker), "B");
+    }
+
+    #[test]
+    fn previous_turn_puts_a_delayer_back() {
+        let mut tracker = sorted_tracker(&[("A", 20, 0), ("B", 15, 0), ("C", 10, 0)]);
+        tracker.next_turn();
+        tracker.delay_until_after(id_of(&tracker, "C"));
+        assert_eq!(names(&tracker), ["B", "C", "A"]);
+
+        tracker.previous_turn();
+        assert_eq!(names(&tracker), ["A", "B", "C"]);
+        assert_eq!(active_name(&tracker), "A");
+        assert_eq!(tracker.entries[0].initiative, 20);
+        assert!(!tracker.entries[0].delaying);
+        // Nobody gets skipped on the way round.
+        tracker.next_turn();
+        assert_eq!(active_name(&tracker), "B");
+        tracker.next_turn();
+        assert_eq!(active_name(&tracker), "C");
+    }
+
+    #[test]
+    fn sorting_mid_fight_keeps_the_current_turn() {
+        let mut tracker = sorted_tracker(&[("A", 20, 0), ("B", 15, 0), ("C", 10, 0)]);
+        for _ in 0..5 {
+            tracker.next_turn();
+        }
+        assert_eq!((active_name(&tracker), tracker.round_count), ("B", 2));
+
+        // C beats A on a re-roll; the turn order moves but B keeps the turn.
+        let c = index_of(&tracker, "C");
+        tracker.entries[c].initiative = 25;
+        tracker.sort_by_initiative();
+        assert_eq!(names(&tracker), ["C", "A", "B"]);
+        assert_eq!((active_name(&tracker), tracker.round_count), ("B", 2));
+
+        tracker.previous_turn();
+        assert_eq!((active_name(&tracker), tracker.round_count), ("A", 2));
+        tracker.next_turn();
+        tracker.next_turn();
+        assert_eq!((active_name(&tracker), tracker.round_count), ("C", 3));
+    }
+
+    #[test]
+    fn the_lair_loses_initiative_ties() {
+        let mut tracker = sorted_tracker(&[("Dragon", 20, 0), ("Rogue", 20, -1), ("Orc", 12, 1)]);
+        tracker.add_entry(InitiativeTrackerEntry::lair());
+        tracker.sort_by_initiative();
+        assert_eq!(names(&tracker), ["Dragon", "Rogue", "Lair Actions", "Orc"]);
+        // A tie the lair was placed in doesn't stick once its count changes.
+        let lair = index_of(&tracker, "Lair Actions");
+        tracker.entries[lair].initiative = 12;
+        tracker.entries[lair].clear_tie_break();
+        tracker.sort_by_initiative();
+        assert_eq!(names(&tracker), ["Dragon", "Rogue", "Orc", "Lair Actions"]);
+    }
+
+    #[test]
+    fn legendary_actions_come_back_at_the_start_of_the_creatures_turn() {
+        let mut tracker = sorted_tracker(&[("Dragon", 20, 0), ("Rogue", 15, 0)]);
+        let dragon = index_of(&tracker, "Dragon");
+        tracker.entries[dragon].legendary_actions = 3;
+        tracker.entries[dragon].legendary_actions_left = 3;
+        tracker.next_turn();
+        tracker.next_turn();
+        assert_eq!(active_name(&tracker), "Rogue");
+        tracker.use_legendary_action(dragon);
+        tracker.use_legendary_action(dragon);
+        assert_eq!(tracker.entries[dragon].legendary_actions_left, 1);
+
+        tracker.next_turn();
+        assert_eq!(active_name(&tracker), "Dragon");
+        assert_eq!(tracker.entries[dragon].legendary_actions_left, 3);
+    }
+
+    #[test]
+    fn previous_turn_gives_back_what_the_turn_start_reset() {
+        let mut tracker = sorted_tracker(&[("A", 20, 0), ("B", 15, 0)]);
+        tracker.next_turn();
+        let b = index_of(&tracker, "B");
+        tracker.entries[b].legendary_actions = 3;
+        tracker.entries[b].legendary_actions_left = 1;
+        tracker.entries[b].readied_action = Some("When the door opens".to_string());
+        tracker.entries[b].is_pc = true;
+        tracker.entries[b].hp_current = 0;
+
+        tracker.next_turn();
+        assert_eq!(tracker.entries[b].legendary_actions_left, 3);
+        assert!(tracker.entries[b].readied_action.is_none());
+        assert_eq!(tracker.death_save_prompt, Some(id_of(&tracker, "B")));
+
+        tracker.previous_turn();
+        assert_eq!(active_name(&tracker), "A");
+        assert_eq!(tracker.entries[b].legendary_actions_left, 1);
+        assert_eq!(
+            tracker.entries[b].readied_action.as_deref(),
+            Some("When the door opens")
+        );
+        assert_eq!(tracker.death_save_prompt, None);
+    }
+
+    #[test]
+    fn previous_turn_puts_an_expired_condition_back_in_its_place() {
+        let mut tracker = sorted_tracker(&[("A", 20, 0), ("B", 15, 0)]);
+        tracker.next_turn();
+        add_timed_condition(&mut tracker, "A", "Blinded", 1, TurnBoundary::End);
+        add_timed_condition(&mut tracker, "A", "Prone", 3, TurnBoundary::Start);
+
+        tracker.next_turn();
+        assert_eq!(rounds_left(&tracker, "A", "Blinded"), None);
+        tracker.previous_turn();
+        let kinds: Vec<&str> = tracker.entries[0]
+            .conditions
+            .iter()
+            .map(|c| c.kind.name())
+            .collect();
+        assert_eq!(kinds, ["Blinded", "Prone"]);
+        assert_eq!(rounds_left(&tracker, "A", "Blinded"), Some(1));
+    }
+
+    /// A sorted tracker with G1 and G2 in one group between A and C.
+    fn grouped_tracker() -> InitiativeTracker {
+        let mut tracker =
+            sorted_tracker(&[("A", 20, 0), ("G1", 15, 0), ("G2", 12, 0), ("C", 10, 0)]);
+        tracker.change_group(index_of(&tracker, "G1"), GroupChange::New);
+        let group_id = tracker.entries[index_of(&tracker, "G1")].group_id.unwrap();
+        tracker.change_group(index_of(&tracker, "G2"), GroupChange::Join(group_id));
+        tracker.sort_by_initiative();
+        tracker
+    }
+
+    #[test]
+    fn a_group_takes_one_turn_together() {
+        let mut tracker = grouped_tracker();
+        assert_eq!(names(&tracker), ["A", "G1", "G2", "C"]);
+        assert_eq!(tracker.entries[index_of(&tracker, "G2")].initiative, 15);
+
+        tracker.next_turn();
+        tracker.next_turn();
+        assert_eq!(active_name(&tracker), "G1");
+        tracker.next_turn();
+        assert_eq!(active_name(&tracker), "C");
+        tracker.next_turn();
+        assert_eq!(active_name(&tracker), "A");
+        assert_eq!(tracker.round_count, 2);
+    }
+
+    #[test]
+    fn conditions_count_down_for_every_group_member() {
+        let mut tracker = grouped_tracker();
+        add_timed_condition(&mut tracker, "G1", "Blinded", 1, TurnBoundary::End);
+        add_timed_condition(&mut tracker, "G2", "Deafened", 1, TurnBoundary::End);
+        add_timed_condition(&mut tracker, "G2", "Prone", 2, TurnBoundary::Start);
+
+        tracker.next_turn();
+        tracker.next_turn();
+        assert_eq!(rounds_left(&tracker, "G2", "Prone"), Some(1));
+        tracker.next_turn();
+        assert!(condition_names(&tracker, "G1").is_empty());
+        assert_eq!(condition_names(&tracker, "G2"), ["Prone"]);
+    }
+
+    #[test]
+    fn a_condition_added_during_the_group_turn_lasts_past_it() {
+        let mut tracker = grouped_tracker();
+        tracker.next_turn();
+        tracker.next_turn();
+        assert_eq!(active_name(&tracker), "G1");
+        for name in ["G1", "G2"] {
+            let index = index_of(&tracker, name);
+            tracker.entries[index].condition_form = ConditionForm {
+                kind: ConditionKind::Restrained,
+                timed: true,
+                rounds: 1,
+                ..ConditionForm::default()
+            };
+            tracker.add_condition(index);
+        }
+
+        tracker.next_turn();
+        assert_eq!(rounds_left(&tracker, "G1", "Restrained"), Some(1));
+        assert_eq!(rounds_left(&tracker, "G2", "Restrained"), Some(1));
+        tracker.next_turn();
+        tracker.next_turn();
+        tracker.next_turn();
+        assert!(condition_names(&tracker, "G1").is_empty());
+        assert!(condition_names(&tracker, "G2").is_empty());
+    }
+
+    #[test]
+    fn a_surprised_creature_at_the_top_loses_its_first_turn() {
+        let mut tracker = sorted_tracker(&[("A", 20, 0), ("B", 15, 0), ("C", 10, 0)]);
+        let a = index_of(&tracker, "A");
+        tracker.entries[a].surprised = true;
+        tracker.sort_by_initiative();
+        assert_eq!(tracker.active_index, None);
+
+        tracker.next_turn();
+        assert_eq!(active_name(&tracker), "B");
+        assert_eq!(tracker.round_count, 1);
+        tracker.next_turn();
+        tracker.next_turn();
+        assert_eq!(active_name(&tracker), "A");
+        assert_eq!(tracker.round_count, 2);
+    }
+
+    #[test]
+    fn revealing_keeps_the_settled_tie_order() {
+        let mut tracker = sorted_tracker(&[("A", 15, 0), ("B", 15, 0), ("C", 10, 0)]);
+        assert_eq!(tracker.tie_groups.len(), 1);
+        // Added at the bottom as an ambusher, after the tie was settled.
+        tracker.move_entry(id_of(&tracker, "A"), id_of(&tracker, "C"), true);
+        let a = index_of(&tracker, "A");
+        tracker.entries[a].hidden = true;
+        assert_eq!(names(&tracker), ["B", "C", "A"]);
+
+        tracker.reveal(a);
+        assert_eq!(names(&tracker), ["A", "B", "C"]);
+        assert!(!tracker.entries[0].hidden);
+    }
+
+    #[test]
+    fn revealing_an_entry_in_place_does_not_move_it() {
+        let mut tracker = sorted_tracker(&[("A", 15, 0), ("B", 15, 0), ("C", 10, 0)]);
+        tracker.next_turn();
+        tracker.next_turn();
+        assert_eq!(active_name(&tracker), "B");
+        tracker.entries[0].hidden = true;
+
+        tracker.reveal(0);
+        assert_eq!(names(&tracker), ["A", "B", "C"]);
+        assert_eq!(active_name(&tracker), "B");
+        // A has acted this round already.
+        tracker.next_turn();
+        assert_eq!(active_name(&tracker), "C");
+        assert_eq!(tracker.round_count, 1);
+    }
+
+    #[test]
+    fn encounter_files_read_back_what_was_saved() {
+        let tracker = grouped_tracker();
+        let path = std::env::temp_dir()
+            .join(format!(
+                "ttrpg_app_{}_{}",
+                std::process::id(),
+                fastrand::u64(..)
+            ))
+            .join("encounter.json");
+
+        save_encounter_to_file(&path, &tracker.entries, &tracker.groups).unwrap();
+        let file = load_encounter_from_file(&path).unwrap();
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+        assert_eq!(file.version, SAVE_VERSION);
+        let summary = |entries: &[InitiativeTrackerEntry]| -> Vec<(u64, String, i32, Option<u64>)> {
+            entries
+                .iter()
+                .map(|e| (e.id, e.name.clone(), e.initiative, e.group_id))
+                .collect()
+        };
+        assert_eq!(summary(&file.entries), summary(&tracker.entries));
+        assert_eq!(file.groups.len(), 1);
+        assert_eq!(file.groups[0].id, tracker.groups[0].id);
+        assert_eq!(file.groups[0].name, "G1");
+    }
+
+    #[test]
+    fn missing_roster_is_an_error() {
+        let path = std::env::temp_dir().join("ttrpg_app_no_such_roster.json");
+        assert!(load_pcs_from_file(&path).is_err());
+    }
+}
@@ -0,0 +1,149 @@
+use super::{InitiativeTracker, InitiativeTrackerEntry};
+use eframe::egui;
+
+impl InitiativeTrackerEntry {
+    /// How hurt a creature looks, for players who shouldn't see monster HP.
+    fn health_descriptor(&self) -> &'static str {
+        if self.death_saves.dead {
+            "Dead"
+        } else if self.hp_current <= 0 {
+            "Down"
+        } else if self.hp_current * 2 <= self.hp_max() {
+            "Bloodied"
+        } else if self.hp_current < self.hp_max() {
+            "Injured"
+        } else {
+            "Healthy"
+        }
+    }
+
+    /// What the players may know about this creature's health and conditions.
+    fn public_status(&self) -> String {
+        let mut status = if self.lair_actions {
+            String::new()
+        } else if self.is_pc {
+            format!("{}/{} HP", self.hp_current, self.hp_max())
+        } else {
+            self.health_descriptor().to_string()
+        };
+        for condition in &self.conditions {
+            if !status.is_empty() {
+                status.push_str(", ");
+            }
+            status.push_str(condition.kind.name());
+        }
+        status
+    }
+}
+
+impl InitiativeTracker {
+    /// The Player View: a second window showing only what the players may see.
+    pub fn player_view(&mut self, ctx: &egui::Context) {
+        if !self.show_player_view {
+            return;
+        }
+        ctx.show_viewport_immediate(
+            egui::ViewportId::from_hash_of("player_view"),
+            egui::ViewportBuilder::default()
+                .with_title("Player View")
+                .with_inner_size([420.0, 600.0]),
+            |ctx, class| {
+                if class == egui::ViewportClass::Embedded {
+                    // No second window on this platform: show it inside the main one.
+                    let mut open = true;
+                    egui::Window::new("Player View")
+                        .open(&mut open)
+                        .show(ctx, |ui| self.player_view_ui(ui));
+                    self.show_player_view = open;
+                } else {
+                    egui::CentralPanel::default().show(ctx, |ui| self.player_view_ui(ui));
+                    if ctx.input(|i| i.viewport().close_requested()) {
+                        self.show_player_view = false;
+                    }
+                }
+            },
+        );
+    }
+
+    fn player_view_ui(&self, ui: &mut egui::Ui) {
+        ui.heading(format!("Round {}", self.round_count));
+        ui.separator();
+        for start in self.turn_starts() {
+            let span = self.turn_span(start);
+            let visible: Vec<&InitiativeTrackerEntry> = self.entries[span.clone()]
+                .iter()
+                .filter(|e| !e.hidden)
+                .collect();
+            if visible.is_empty() {
+                continue;
+            }
+            let is_active = self.active_index == Some(start);
+            let marker = if is_active { "▶" } else { "  " };
+            let name =
+                egui::RichText::new(format!("{} {}", marker, self.turn_name(start))).size(20.0);
+            let name = if is_active {
+                name.strong().color(egui::Color32::from_rgb(255, 80, 80))
+            } else {
+                name
+            };
+            ui.label(name);
+            let grouped = self.entries[start].group_id.is_some();
+            for entry in visible {
+                let status = entry.public_status();
+                let line = match (grouped, status.is_empty()) {
+                    (true, _) => format!("      {}: {}", entry.name, status),
+                    (false, true) => continue,
+                    (false, false) => format!("      {}", status),
+                };
+                ui.label(egui::RichText::new(line).size(16.0));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::apps::initiative_tracker::conditions::{Condition, ConditionKind};
+
+    fn monster(hp_current: i32) -> InitiativeTrackerEntry {
+        InitiativeTrackerEntry {
+            hp_current,
+            hp_total: 20,
+            ..InitiativeTrackerEntry::default()
+        }
+    }
+
+    #[test]
+    fn describes_health_in_bands() {
+        assert_eq!(monster(20).health_descriptor(), "Healthy");
+        assert_eq!(monster(11).health_descriptor(), "Injured");
+        assert_eq!(monster(10).health_descriptor(), "Bloodied");
+        assert_eq!(monster(9).health_descriptor(), "Bloodied");
+        assert_eq!(monster(0).health_descriptor(), "Down");
+        let mut dead = monster(0);
+        dead.death_saves.dead = true;
+        assert_eq!(dead.health_descriptor(), "Dead");
+    }
+
+    #[test]
+    fn shows_hp_only_for_pcs() {
+        let mut goblin = monster(9);
+        goblin
+            .conditions
+            .push(Condition::indefinite(ConditionKind::Prone));
+        assert_eq!(goblin.public_status(), "Bloodied, Prone");
+
+        let pc = InitiativeTrackerEntry {
+            is_pc: true,
+            ..monster(9)
+        };
+        assert_eq!(pc.public_status(), "9/20 HP");
+
+        let lair = InitiativeTrackerEntry {
+            lair_actions: true,
+            ..monster(20)
+        };
+        assert_eq!(lair.public_status(), "");
+    }
+}
@@ -0,0 +1,131 @@
+use super::conditions::Condition;
+use serde::{Deserialize, Serialize};
+
+/// How many turn changes "Previous Turn" can step back over.
+const MAX_TURN_STEPS: usize = 100;
+
+/// What a turn boundary did to one timed condition.
+#[derive(Clone, Serialize, Deserialize)]
+pub enum TickEffect {
+    /// The condition was added during this turn, so the tick didn't count.
+    Skipped,
+    CountedDown,
+    /// The last round ran out and the condition was removed from `index` in the entry's list.
+    Expired {
+        condition: Condition,
+        index: usize,
+    },
+}
+
+/// A tick of one entry's condition, identified by the condition's id.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ConditionTick {
+    pub entry_id: u64,
+    pub condition_id: u64,
+    pub effect: TickEffect,
+}
+
+/// What starting an entry's turn reset, as it was before.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TurnStartReset {
+    pub entry_id: u64,
+    pub legendary_actions_left: u32,
+    pub readied_action: Option<String>,
+}
+
+/// Where a delayer was in the order before they delayed.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DelayMove {
+    pub entry_id: u64,
+    pub index: usize,
+    pub initiative: i32,
+    pub tie_break_rank: Option<u32>,
+    pub roll_off: Option<i32>,
+    /// Every entry's `delay_rank` before the delay re-ranked the ties.
+    pub delay_ranks: Vec<(u64, Option<u32>)>,
+}
+
+/// One turn change, with what it takes to step back over it.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TurnStep {
+    /// The active entry before the change; `None` if combat hadn't started.
+    pub active_id: Option<u64>,
+    /// The round before the change.
+    pub round: u32,
+    /// Condition ticks in the order they happened.
+    pub ticks: Vec<ConditionTick>,
+    /// Entries whose surprise wore off during the change.
+    pub surprise_ended: Vec<u64>,
+    /// Delayers whose delay was over by the end of the change.
+    pub delaying_ended: Vec<u64>,
+    /// Turn starts in the order they happened.
+    pub turn_starts: Vec<TurnStartReset>,
+    /// Whose death save was due before the change.
+    pub death_save_prompt: Option<u64>,
+    /// Set if the change was someone delaying.
+    pub delay: Option<DelayMove>,
+}
+
+/// The turn changes made so far, most recent last.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct TurnSteps {
+    steps: Vec<TurnStep>,
+}
+
+impl TurnSteps {
+    /// Starts recording a turn change away from `active_id` in `round`.
+    pub fn begin(&mut self, active_id: Option<u64>, round: u32, death_save_prompt: Option<u64>) {
+        self.steps.push(TurnStep {
+            active_id,
+            round,
+            ticks: Vec::new(),
+            surprise_ended: Vec::new(),
+            delaying_ended: Vec::new(),
+            turn_starts: Vec::new(),
+            death_save_prompt,
+            delay: None,
+        });
+        if self.steps.len() > MAX_TURN_STEPS {
+            self.steps.remove(0);
+        }
+    }
+
+    /// Adds ticks to the turn change being recorded, if any.
+    pub fn record_ticks(&mut self, ticks: Vec<ConditionTick>) {
+        if let Some(step) = self.steps.last_mut() {
+            step.ticks.extend(ticks);
+        }
+    }
+
+    pub fn record_surprise_ended(&mut self, ids: Vec<u64>) {
+        if let Some(step) = self.steps.last_mut() {
+            step.surprise_ended.extend(ids);
+        }
+    }
+
+    pub fn record_delaying_ended(&mut self, ids: impl IntoIterator<Item = u64>) {
+        if let Some(step) = self.steps.last_mut() {
+            step.delaying_ended.extend(ids);
+        }
+    }
+
+    pub fn record_turn_start(&mut self, reset: TurnStartReset) {
+        if let Some(step) = self.steps.last_mut() {
+            step.turn_starts.push(reset);
+        }
+    }
+
+    pub fn record_delay(&mut self, delay: DelayMove) {
+        if let Some(step) = self.steps.last_mut() {
+            step.delay = Some(delay);
+        }
+    }
+
+    pub fn pop(&mut self) -> Option<TurnStep> {
+        self.steps.pop()
+    }
+
+    pub fn clear(&mut self) {
+        self.steps.clear();
+    }
+}
@@ -1 +1,3 @@
+pub mod bestiary;
+pub mod dice_roller;
 pub mod initiative_tracker;
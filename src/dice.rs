@@ -0,0 +1,683 @@
+// Dice engine shared by the sub-apps: parses standard dice notation and rolls it.
+//
+// Supported notation (case-insensitive, whitespace is ignored):
+//   2d6+3       dice and flat modifiers, joined by + and -
+//   d20, d%     the count defaults to 1, `%` means 100 sides
+//   4d6kh3      keep the highest 3 (`k3` is the same), `kl1` keeps the lowest
+//   4d6dl1      drop the lowest 1, `dh1` drops the highest
+//   3d6!        exploding dice: every maximum roll adds another die
+//   2d6r2       reroll each die showing 2 or less, once (e.g. Great Weapon Fighting)
+//   1d20+5 adv  roll single d20s twice and keep the higher (`dis` keeps the lower)
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Upper bounds that keep a typo like `1000000d6` from freezing the UI.
+const MAX_DICE: u32 = 1000;
+const MAX_SIDES: u32 = 1000;
+/// How many extra dice a single exploding die may add.
+const MAX_EXPLOSIONS: u32 = 100;
+
+/// Whether a d20 roll is made with advantage, disadvantage or neither.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RollMode {
+    #[default]
+    Normal,
+    Advantage,
+    Disadvantage,
+}
+
+impl RollMode {
+    pub fn label(self) -> &'static str {
+        match self {
+            RollMode::Normal => "Normal",
+            RollMode::Advantage => "Adv",
+            RollMode::Disadvantage => "Dis",
+        }
+    }
+}
+
+/// A problem found while parsing dice notation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiceError {
+    message: String,
+}
+
+impl DiceError {
+    fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for DiceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for DiceError {}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Keep {
+    Highest(u32),
+    Lowest(u32),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct DiceTerm {
+    count: u32,
+    sides: u32,
+    keep: Option<Keep>,
+    explode: bool,
+    /// Reroll (once) any die showing this value or lower.
+    reroll_at_most: Option<u32>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum TermKind {
+    Constant(i32),
+    Dice(DiceTerm),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Term {
+    negative: bool,
+    kind: TermKind,
+}
+
+/// A parsed dice expression that can be rolled any number of times.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DiceExpr {
+    terms: Vec<Term>,
+}
+
+/// One die as it landed.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DieRoll {
+    pub value: i32,
+    /// False for dice removed by keep/drop or by advantage.
+    pub kept: bool,
+    /// The first roll if this die was rerolled.
+    pub rerolled_from: Option<i32>,
+    /// True if this die rolled its maximum and added another die.
+    pub exploded: bool,
+}
+
+/// The outcome of one term of an expression.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum TermRoll {
+    Constant(i32),
+    Dice {
+        /// The term as written, e.g. `4d6kh3`.
+        notation: String,
+        dice: Vec<DieRoll>,
+    },
+}
+
+/// The outcome of rolling a whole expression.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Roll {
+    pub total: i32,
+    /// Each term paired with whether it was subtracted.
+    pub terms: Vec<(bool, TermRoll)>,
+}
+
+impl fmt::Display for Roll {
+    // e.g. `4d6kh3 [6, 5, 4, (2)] + 3 = 18`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (negative, term)) in self.terms.iter().enumerate() {
+            match (i, negative) {
+                (0, true) => write!(f, "-")?,
+                (0, false) => {}
+                (_, true) => write!(f, " - ")?,
+                (_, false) => write!(f, " + ")?,
+            }
+            match term {
+                TermRoll::Constant(value) => write!(f, "{}", value)?,
+                TermRoll::Dice { notation, dice } => {
+                    let faces: Vec<String> = dice
+                        .iter()
+                        .map(|die| {
+                            let mut face = match die.rerolled_from {
+                                Some(first) => format!("{}→{}", first, die.value),
+                                None => die.value.to_string(),
+                            };
+                            if die.exploded {
+                                face.push('!');
+                            }
+                            if die.kept {
+                                face
+                            } else {
+                                format!("({})", face)
+                            }
+                        })
+                        .collect();
+                    write!(f, "{} [{}]", notation, faces.join(", "))?;
+                }
+            }
+        }
+        write!(f, " = {}", self.total)
+    }
+}
+
+impl DiceExpr {
+    /// Parses dice notation such as `2d6+3` or `1d20+5 adv`.
+    pub fn parse(notation: &str) -> Result<Self, DiceError> {
+        let lowered = notation.trim().to_lowercase();
+        // A trailing `adv`/`dis` word applies to the single d20s in the expression.
+        let (body, advantage) = match lowered.rsplit_once(char::is_whitespace) {
+            Some((body, "adv" | "advantage")) => (body, RollMode::Advantage),
+            Some((body, "dis" | "disadvantage")) => (body, RollMode::Disadvantage),
+            _ => (lowered.as_str(), RollMode::Normal),
+        };
+        let chars: Vec<char> = body.chars().filter(|c| !c.is_whitespace()).collect();
+        if chars.is_empty() {
+            return Err(DiceError::new("Enter some dice, e.g. 2d6+3"));
+        }
+
+        let mut parser = Parser { chars, pos: 0 };
+        let mut expr = parser.parse_expr()?;
+        expr.apply_advantage(advantage);
+        if expr.max_magnitude() > i32::MAX as u64 {
+            return Err(DiceError::new("That could roll a total too large to count"));
+        }
+        Ok(expr)
+    }
+
+    /// The largest total the expression could reach in either direction.
+    fn max_magnitude(&self) -> u64 {
+        self.terms
+            .iter()
+            .map(|term| match &term.kind {
+                TermKind::Constant(value) => value.unsigned_abs() as u64,
+                TermKind::Dice(dice) => {
+                    let per_die = if dice.explode {
+                        MAX_EXPLOSIONS as u64 + 1
+                    } else {
+                        1
+                    };
+                    dice.count as u64 * dice.sides as u64 * per_die
+                }
+            })
+            .fold(0, u64::saturating_add)
+    }
+
+    /// A d20 check with a flat modifier, e.g. an initiative roll.
+    pub fn d20_check(modifier: i32, mode: RollMode) -> Self {
+        let mut terms = vec![Term {
+            negative: false,
+            kind: TermKind::Dice(DiceTerm {
+                count: 1,
+                sides: 20,
+                keep: None,
+                explode: false,
+                reroll_at_most: None,
+            }),
+        }];
+        if modifier != 0 {
+            terms.push(Term {
+                negative: modifier < 0,
+                // `i32::MIN` has no positive counterpart, so saturate it.
+                kind: TermKind::Constant(
+                    i32::try_from(modifier.unsigned_abs()).unwrap_or(i32::MAX),
+                ),
+            });
+        }
+        let mut expr = Self { terms };
+        expr.apply_advantage(mode);
+        expr
+    }
+
+    /// Turns every lone `1d20` into `2d20kh1` (advantage) or `2d20kl1` (disadvantage).
+    fn apply_advantage(&mut self, advantage: RollMode) {
+        let keep = match advantage {
+            RollMode::Normal => return,
+            RollMode::Advantage => Keep::Highest(1),
+            RollMode::Disadvantage => Keep::Lowest(1),
+        };
+        for term in self.terms.iter_mut() {
+            if let TermKind::Dice(dice) = &mut term.kind
+                && dice.count == 1
+                && dice.sides == 20
+                && dice.keep.is_none()
+            {
+                dice.count = 2;
+                dice.keep = Some(keep);
+            }
+        }
+    }
+
+    /// Rolls the expression with the given random number generator.
+    pub fn roll(&self, rng: &mut fastrand::Rng) -> Roll {
+        let mut total: i32 = 0;
+        let mut terms = Vec::with_capacity(self.terms.len());
+        for term in &self.terms {
+            let (value, roll) = match &term.kind {
+                TermKind::Constant(value) => (*value, TermRoll::Constant(*value)),
+                TermKind::Dice(dice) => {
+                    let rolled = dice.roll(rng);
+                    let value = rolled.iter().filter(|d| d.kept).map(|d| d.value).sum();
+                    (
+                        value,
+                        TermRoll::Dice {
+                            notation: dice.to_string(),
+                            dice: rolled,
+                        },
+                    )
+                }
+            };
+            // Saturate rather than overflow, e.g. for a huge initiative modifier.
+            total = if term.negative {
+                total.saturating_sub(value)
+            } else {
+                total.saturating_add(value)
+            };
+            terms.push((term.negative, roll));
+        }
+        Roll { total, terms }
+    }
+}
+
+impl fmt::Display for DiceExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, term) in self.terms.iter().enumerate() {
+            if term.negative {
+                write!(f, "-")?;
+            } else if i > 0 {
+                write!(f, "+")?;
+            }
+            match &term.kind {
+                TermKind::Constant(value) => write!(f, "{}", value)?,
+                TermKind::Dice(dice) => write!(f, "{}", dice)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl DiceTerm {
+    fn roll_one(&self, rng: &mut fastrand::Rng) -> i32 {
+        rng.i32(1..=self.sides as i32)
+    }
+
+    fn roll(&self, rng: &mut fastrand::Rng) -> Vec<DieRoll> {
+        let mut dice = Vec::with_capacity(self.count as usize);
+        for _ in 0..self.count {
+            let first = self.roll_one(rng);
+            let mut die = DieRoll {
+                value: first,
+                kept: true,
+                rerolled_from: None,
+                exploded: false,
+            };
+            if let Some(threshold) = self.reroll_at_most
+                && first <= threshold as i32
+            {
+                die.value = self.roll_one(rng);
+                die.rerolled_from = Some(first);
+            }
+            dice.push(die);
+
+            if self.explode {
+                let mut explosions = 0;
+                while dice.last().is_some_and(|d| d.value == self.sides as i32)
+                    && explosions < MAX_EXPLOSIONS
+                {
+                    if let Some(last) = dice.last_mut() {
+                        last.exploded = true;
+                    }
+                    dice.push(DieRoll {
+                        value: self.roll_one(rng),
+                        kept: true,
+                        rerolled_from: None,
+                        exploded: false,
+                    });
+                    explosions += 1;
+                }
+            }
+        }
+
+        if let Some(keep) = self.keep {
+            // Rank the dice by value and drop everything past the first `n`.
+            let mut order: Vec<usize> = (0..dice.len()).collect();
+            let (n, highest) = match keep {
+                Keep::Highest(n) => (n, true),
+                Keep::Lowest(n) => (n, false),
+            };
+            order.sort_by_key(|&i| dice[i].value);
+            if highest {
+                order.reverse();
+            }
+            for &i in order.iter().skip(n as usize) {
+                dice[i].kept = false;
+            }
+        }
+        dice
+    }
+}
+
+impl fmt::Display for DiceTerm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}d{}", self.count, self.sides)?;
+        match self.keep {
+            Some(Keep::Highest(n)) => write!(f, "kh{}", n)?,
+            Some(Keep::Lowest(n)) => write!(f, "kl{}", n)?,
+            None => {}
+        }
+        if self.explode {
+            write!(f, "!")?;
+        }
+        if let Some(threshold) = self.reroll_at_most {
+            write!(f, "r{}", threshold)?;
+        }
+        Ok(())
+    }
+}
+
+// A small recursive-descent parser over the notation with whitespace removed.
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn number(&mut self) -> Result<Option<u32>, DiceError> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        if start == self.pos {
+            return Ok(None);
+        }
+        let digits: String = self.chars[start..self.pos].iter().collect();
+        digits
+            .parse()
+            .map(Some)
+            .map_err(|_| DiceError::new(format!("{} is too large", digits)))
+    }
+
+    fn expect_number(&mut self, after: &str) -> Result<u32, DiceError> {
+        self.number()?
+            .ok_or_else(|| DiceError::new(format!("Expected a number after '{}'", after)))
+    }
+
+    fn parse_expr(&mut self) -> Result<DiceExpr, DiceError> {
+        let mut terms = Vec::new();
+        let mut negative = self.eat('-');
+        if !negative {
+            self.eat('+');
+        }
+        loop {
+            let kind = self.parse_term()?;
+            terms.push(Term { negative, kind });
+            match self.peek() {
+                None => break,
+                Some('+') => negative = false,
+                Some('-') => negative = true,
+                Some(c) => {
+                    return Err(DiceError::new(format!("Unexpected '{}'", c)));
+                }
+            }
+            self.pos += 1;
+        }
+        Ok(DiceExpr { terms })
+    }
+
+    fn parse_term(&mut self) -> Result<TermKind, DiceError> {
+        let count = self.number()?;
+        if !self.eat('d') {
+            return match count {
+                Some(value) => i32::try_from(value)
+                    .map(TermKind::Constant)
+                    .map_err(|_| DiceError::new(format!("{} is too large", value))),
+                None => match self.peek() {
+                    Some(c) => Err(DiceError::new(format!("Unexpected '{}'", c))),
+                    None => Err(DiceError::new("Expression ends unexpectedly")),
+                },
+            };
+        }
+
+        let count = count.unwrap_or(1);
+        let sides = if self.eat('%') {
+            100
+        } else {
+            self.expect_number("d")?
+        };
+        if count == 0 || count > MAX_DICE {
+            return Err(DiceError::new(format!(
+                "Roll between 1 and {} dice at a time",
+                MAX_DICE
+            )));
+        }
+        if sides == 0 || sides > MAX_SIDES {
+            return Err(DiceError::new(format!(
+                "Dice need between 1 and {} sides",
+                MAX_SIDES
+            )));
+        }
+
+        let mut term = DiceTerm {
+            count,
+            sides,
+            keep: None,
+            explode: false,
+            reroll_at_most: None,
+        };
+        loop {
+            if self.eat('k') {
+                let keep_lowest = self.eat('l');
+                if !keep_lowest {
+                    self.eat('h');
+                }
+                let n = self.expect_number("k")?;
+                term.keep = Some(if keep_lowest {
+                    Keep::Lowest(n)
+                } else {
+                    Keep::Highest(n)
+                });
+            } else if self.peek() == Some('d')
+                && matches!(self.chars.get(self.pos + 1), Some('h' | 'l'))
+            {
+                self.pos += 1;
+                let drop_highest = self.eat('h');
+                if !drop_highest {
+                    self.eat('l');
+                }
+                let n = self.expect_number("d")?;
+                // Dropping is keeping the rest from the other end.
+                let rest = count.saturating_sub(n);
+                term.keep = Some(if drop_highest {
+                    Keep::Lowest(rest)
+                } else {
+                    Keep::Highest(rest)
+                });
+            } else if self.eat('!') {
+                if sides < 2 {
+                    return Err(DiceError::new("A one-sided die can't explode"));
+                }
+                term.explode = true;
+            } else if self.eat('r') {
+                let threshold = self.expect_number("r")?;
+                if threshold >= sides {
+                    return Err(DiceError::new(format!(
+                        "Rerolling {} or lower on a d{} rerolls every die",
+                        threshold, sides
+                    )));
+                }
+                term.reroll_at_most = Some(threshold);
+            } else {
+                break;
+            }
+        }
+        Ok(TermKind::Dice(term))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(notation: &str) -> DiceExpr {
+        DiceExpr::parse(notation).unwrap()
+    }
+
+    /// Rolls `notation` with a fixed seed.
+    fn roll(notation: &str, seed: u64) -> Roll {
+        parse(notation).roll(&mut fastrand::Rng::with_seed(seed))
+    }
+
+    #[test]
+    fn parses_dice_and_modifiers() {
+        assert_eq!(parse("2d6+3").to_string(), "2d6+3");
+        assert_eq!(parse("d20").to_string(), "1d20");
+        assert_eq!(parse("d%").to_string(), "1d100");
+        assert_eq!(parse("2D6").to_string(), "2d6");
+        assert_eq!(parse("+1d8").to_string(), "1d8");
+    }
+
+    #[test]
+    fn ignores_whitespace() {
+        assert_eq!(parse(" 2 d 6 +  3 "), parse("2d6+3"));
+    }
+
+    #[test]
+    fn parses_negative_terms() {
+        assert_eq!(parse("-1d4-2").to_string(), "-1d4-2");
+        for seed in 0..20 {
+            let rolled = roll("-1d4-2", seed);
+            assert!((-6..=-3).contains(&rolled.total), "{}", rolled);
+        }
+    }
+
+    #[test]
+    fn parses_keep_drop_explode_and_reroll() {
+        assert_eq!(parse("4d6kh3").to_string(), "4d6kh3");
+        assert_eq!(parse("4d6k3").to_string(), "4d6kh3");
+        assert_eq!(parse("2d20kl1").to_string(), "2d20kl1");
+        assert_eq!(parse("4d6dl1").to_string(), "4d6kh3");
+        assert_eq!(parse("4d6dh1").to_string(), "4d6kl3");
+        assert_eq!(parse("3d6!").to_string(), "3d6!");
+        assert_eq!(parse("2d6r2").to_string(), "2d6r2");
+    }
+
+    #[test]
+    fn applies_advantage_to_lone_d20s() {
+        assert_eq!(parse("1d20+5 adv").to_string(), "2d20kh1+5");
+        assert_eq!(parse("d20 dis").to_string(), "2d20kl1");
+        // Only single d20s are affected.
+        assert_eq!(parse("2d20 adv").to_string(), "2d20");
+    }
+
+    #[test]
+    fn rejects_malformed_notation() {
+        for notation in [
+            "", "   ", "2d", "d", "2d6+", "abc", "2d6x", "1dF", "0d6", "1d0", "1001d6", "1d1001",
+            "2d6r6", "1d1!", "4d6k",
+        ] {
+            assert!(DiceExpr::parse(notation).is_err(), "{:?} parsed", notation);
+        }
+    }
+
+    #[test]
+    fn rejects_totals_that_overflow() {
+        assert!(DiceExpr::parse("2147483648").is_err());
+        assert!(DiceExpr::parse("2147483647+1").is_err());
+        assert!(DiceExpr::parse("-2147483647-1d4").is_err());
+        assert!(DiceExpr::parse("2147483647").is_ok());
+        // The largest dice pool is still fine.
+        assert!(DiceExpr::parse("1000d1000!").is_ok());
+    }
+
+    #[test]
+    fn same_seed_rolls_the_same() {
+        let a = roll("4d6kh3+2d8!-1", 42);
+        let b = roll("4d6kh3+2d8!-1", 42);
+        assert_eq!(a.to_string(), b.to_string());
+    }
+
+    #[test]
+    fn totals_stay_in_range() {
+        for seed in 0..100 {
+            let total = roll("3d6+2", seed).total;
+            assert!((5..=20).contains(&total));
+        }
+    }
+
+    #[test]
+    fn keeps_the_right_dice() {
+        for seed in 0..50 {
+            let rolled = roll("4d6kh3", seed);
+            let TermRoll::Dice { dice, .. } = &rolled.terms[0].1 else {
+                panic!("expected dice");
+            };
+            let kept: Vec<i32> = dice.iter().filter(|d| d.kept).map(|d| d.value).collect();
+            let dropped: Vec<i32> = dice.iter().filter(|d| !d.kept).map(|d| d.value).collect();
+            assert_eq!(kept.len(), 3);
+            assert_eq!(dropped.len(), 1);
+            assert!(kept.iter().all(|&k| k >= dropped[0]));
+            assert_eq!(rolled.total, kept.iter().sum::<i32>());
+        }
+    }
+
+    #[test]
+    fn rerolls_low_dice_once() {
+        for seed in 0..50 {
+            let rolled = roll("10d6r2", seed);
+            let TermRoll::Dice { dice, .. } = &rolled.terms[0].1 else {
+                panic!("expected dice");
+            };
+            for die in dice {
+                match die.rerolled_from {
+                    Some(first) => assert!(first <= 2),
+                    None => assert!(die.value > 2),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn exploding_dice_add_a_die_per_maximum() {
+        for seed in 0..50 {
+            let rolled = roll("5d2!", seed);
+            let TermRoll::Dice { dice, .. } = &rolled.terms[0].1 else {
+                panic!("expected dice");
+            };
+            let exploded = dice.iter().filter(|d| d.exploded).count();
+            assert_eq!(dice.len(), 5 + exploded);
+            assert!(dice.iter().filter(|d| d.exploded).all(|d| d.value == 2));
+        }
+    }
+
+    #[test]
+    fn d20_check_handles_extreme_modifiers() {
+        let mut rng = fastrand::Rng::with_seed(7);
+        assert_eq!(
+            DiceExpr::d20_check(-3, RollMode::Normal).to_string(),
+            "1d20-3"
+        );
+        assert_eq!(
+            DiceExpr::d20_check(2, RollMode::Advantage).to_string(),
+            "2d20kh1+2"
+        );
+        let lowest = DiceExpr::d20_check(i32::MIN, RollMode::Normal).roll(&mut rng);
+        // d20 - i32::MAX, since the modifier saturates.
+        assert!((i32::MIN + 2..=i32::MIN + 21).contains(&lowest.total));
+        let highest = DiceExpr::d20_check(i32::MAX, RollMode::Normal).roll(&mut rng);
+        assert_eq!(highest.total, i32::MAX);
+    }
+}
@@ -1,14 +1,30 @@
 use eframe::{egui, run_native, App, NativeOptions};
+use serde::{Deserialize, Serialize};
 
 // Import apps
 mod apps;
-use apps::initiative_tracker::InitiativeTracker;
+mod dice;
+use apps::bestiary::Bestiary;
+use apps::dice_roller::DiceRoller;
+use apps::initiative_tracker::{InitiativeTracker, InitiativeTrackerEntry};
 
 // Trait for all sub-apps in the TTRPG suite.
 // Each sub-app must implement this to provide its name and UI logic.
 trait TtrpgSubApp {
     fn name(&self) -> &'static str;
     fn update_ui(&mut self, ctx: &egui::Context);
+    // Restore state saved by a previous session. Sub-apps without state can skip this.
+    fn load_state(&mut self, _storage: &dyn eframe::Storage) {}
+    // Save state so it survives an app restart.
+    fn save_state(&self, _storage: &mut dyn eframe::Storage) {}
+    // Combatants this sub-app wants added to the initiative tracker, e.g. from the bestiary.
+    fn take_combatants(&mut self) -> Vec<InitiativeTrackerEntry> {
+        Vec::new()
+    }
+    // Receive combatants sent over by another sub-app.
+    fn receive_combatants(&mut self, _combatants: &[InitiativeTrackerEntry]) {}
+    // Draw extra windows that stay open while another sub-app is shown, e.g. the Player View.
+    fn update_viewports(&mut self, _ctx: &egui::Context) {}
 }
 
 // Implement the trait for InitiativeTracker
@@ -19,9 +35,66 @@ impl TtrpgSubApp for InitiativeTracker {
     fn update_ui(&mut self, ctx: &egui::Context) {
         self.update_ui(ctx);
     }
+    fn load_state(&mut self, storage: &dyn eframe::Storage) {
+        if let Some(saved) = InitiativeTracker::load_state(storage) {
+            *self = saved;
+        }
+    }
+    fn save_state(&self, storage: &mut dyn eframe::Storage) {
+        self.save_state(storage);
+    }
+    fn receive_combatants(&mut self, combatants: &[InitiativeTrackerEntry]) {
+        self.add_combatants(combatants);
+    }
+    fn update_viewports(&mut self, ctx: &egui::Context) {
+        self.player_view(ctx);
+    }
 }
 
+// Implement the trait for DiceRoller
+impl TtrpgSubApp for DiceRoller {
+    fn name(&self) -> &'static str {
+        "Dice Roller"
+    }
+    fn update_ui(&mut self, ctx: &egui::Context) {
+        self.update_ui(ctx);
+    }
+    fn load_state(&mut self, storage: &dyn eframe::Storage) {
+        if let Some(saved) = DiceRoller::load_state(storage) {
+            *self = saved;
+        }
+    }
+    fn save_state(&self, storage: &mut dyn eframe::Storage) {
+        self.save_state(storage);
+    }
+}
+
+// Implement the trait for Bestiary
+impl TtrpgSubApp for Bestiary {
+    fn name(&self) -> &'static str {
+        "Bestiary"
+    }
+    fn update_ui(&mut self, ctx: &egui::Context) {
+        self.update_ui(ctx);
+    }
+    fn load_state(&mut self, storage: &dyn eframe::Storage) {
+        if let Some(saved) = Bestiary::load_state(storage) {
+            *self = saved;
+        }
+    }
+    fn save_state(&self, storage: &mut dyn eframe::Storage) {
+        self.save_state(storage);
+    }
+    fn take_combatants(&mut self) -> Vec<InitiativeTrackerEntry> {
+        self.take_combatants()
+    }
+}
+
+// Key for the current view in eframe's persistent storage.
+const VIEW_STORAGE_KEY: &str = "current_view";
+
 // Enum for current view
+#[derive(Serialize, Deserialize)]
 enum AppView {
     LandingPage,
     SubApp(usize), // Index into sub_apps
@@ -42,6 +115,8 @@ impl Default for TtrpgApp {
             // Add new sub-apps to this vector to make them appear in the sidebar.
             sub_apps: vec![
                 Box::new(InitiativeTracker::default()),
+                Box::new(DiceRoller::default()),
+                Box::new(Bestiary::default()),
                 // Box::new(OtherSubApp::default()),
             ],
             sidebar_open: false, // Sidebar starts closed
@@ -49,9 +124,39 @@ impl Default for TtrpgApp {
     }
 }
 
+impl TtrpgApp {
+    // Build the app, restoring the previous session from storage if there is one.
+    fn new(cc: &eframe::CreationContext<'_>) -> Self {
+        let mut app = Self::default();
+        if let Some(storage) = cc.storage {
+            for sub_app in app.sub_apps.iter_mut() {
+                sub_app.load_state(storage);
+            }
+            if let Some(view) = eframe::get_value::<AppView>(storage, VIEW_STORAGE_KEY) {
+                // Guard against a saved index for a sub-app that no longer exists.
+                app.current_view = match view {
+                    AppView::SubApp(i) if i >= app.sub_apps.len() => AppView::LandingPage,
+                    view => view,
+                };
+            }
+        }
+        app
+    }
+}
+
 // Implement the `App` trait for our main application shell
 impl App for TtrpgApp {
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, VIEW_STORAGE_KEY, &self.current_view);
+        for sub_app in self.sub_apps.iter() {
+            sub_app.save_state(storage);
+        }
+    }
+
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        for sub_app in self.sub_apps.iter_mut() {
+            sub_app.update_viewports(ctx);
+        }
         match self.current_view {
             AppView::LandingPage => {
                 // Sidebar is always shown on the landing page
@@ -100,6 +205,18 @@ impl App for TtrpgApp {
 
                 // Render the selected sub-app's UI in the central panel
                 self.sub_apps[i].update_ui(ctx);
+
+                // Pass combatants between sub-apps, e.g. from the bestiary to the tracker.
+                let combatants: Vec<InitiativeTrackerEntry> = self
+                    .sub_apps
+                    .iter_mut()
+                    .flat_map(|app| app.take_combatants())
+                    .collect();
+                if !combatants.is_empty() {
+                    for app in self.sub_apps.iter_mut() {
+                        app.receive_combatants(&combatants);
+                    }
+                }
             }
         }
     }
@@ -111,6 +228,6 @@ fn main() {
     let _ = run_native(
         "TTRPG Utilities",
         window_options,
-        Box::new(|_cc| Ok(Box::new(TtrpgApp::default()))),
+        Box::new(|cc| Ok(Box::new(TtrpgApp::new(cc)))),
     );
 }